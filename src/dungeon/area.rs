@@ -1,13 +1,32 @@
-use rand::prelude::*;
-use crate::vectors::Vector2Int;
+use rand::{prelude::*, rngs::StdRng};
+use std::collections::{HashMap, HashSet};
+use crate::vectors::{Vector2Int, ORTHO_DIRECTIONS};
 
+use super::cycles::{CycleTemplate, MissionGraph};
+use super::difficulty::DifficultyBand;
+use super::events::GenerationEvent;
 use super::room::{Room, RoomGenerator};
 use super::tunnels::Tunneler;
 
+// rolls of a tunnel that runs into a room sealed by a mission lock
+const TUNNEL_ATTEMPTS: usize = 10;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Area {
     pub rooms: Vec<Room>,
     pub paths: Vec<Vec<Vector2Int>>,
+    // room index pairs joined by the matching paths
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub connections: Vec<(usize, usize)>,
+    // set by the cycle strategy, its edges match the connections
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mission: Option<MissionGraph>,
+    // min distance between corridor ends within a room, random points when None
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub endpoint_spacing: Option<f32>,
+    // overrides or blends with the room depths, see Dungeon::get_difficulties
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub difficulty: Option<DifficultyBand>,
     pub tunneler: Tunneler,
     pub room_generator: RoomGenerator,
     pub connection_strategy: ConnectionStrategy
@@ -23,7 +42,11 @@ impl Area {
             tunneler,
             connection_strategy,
             rooms: Vec::new(),
-            paths: Vec::new()
+            paths: Vec::new(),
+            connections: Vec::new(),
+            mission: None,
+            endpoint_spacing: None,
+            difficulty: None
         }
     }
     pub fn get_bounds(&self) -> (Vector2Int, Vector2Int) {
@@ -37,8 +60,8 @@ impl Area {
         let bounds = self.get_bounds();
         Vector2Int::new(bounds.1.x - bounds.0.x, bounds.1.y - bounds.0.y)
     }
-    pub fn shift(&mut self, base_x: i32, base_y: i32) {
-        // translate the entire area by offset
+    pub fn shift(&mut self, base_x: i32, base_y: i32) -> Vector2Int {
+        // translate the entire area by offset, returns the applied offset
         let bounds = self.get_bounds();
         let dx = base_x - bounds.0.x;
         let dy = base_y - bounds.0.y;
@@ -53,12 +76,47 @@ impl Area {
                 *v += d;
             }
         }
+        d
     }
-    pub fn generate_rooms(&mut self) {
-        let (rooms, connections) = self.room_generator.get_generator()();
+    pub fn generate_rooms(&mut self, rng: &mut StdRng, observer: &mut dyn FnMut(GenerationEvent)) {
+        let (mut rooms, mut connections) = self.room_generator.get_generator()(rng, observer);
+
+        // cycles replace the generator's tree with the mission graph's ring
+        self.mission = self.connection_strategy.get_mission(&mut rooms);
+        if let Some(mission) = &self.mission {
+            connections = mission.get_connections();
+        }
         
         // generate connections
-        self.paths = self.connection_strategy.get_connections_generator()(&self.tunneler, &rooms, &connections);
+        let connector = self.tunneler.get_connector();
+        let spacing = self.endpoint_spacing;
+        let mut endpoints: HashMap<usize, Vec<Vector2Int>> = HashMap::new();
+        // the room behind a mission lock is only entered through it,
+        // any other tunnel touching it would lead around the lock
+        let sealed = self.mission.as_ref().and_then(|m| m.get_locked_room());
+        let sealed_tiles = sealed.map(|i| get_touching_tiles(&rooms[i])).unwrap_or_default();
+        let mut joiner = |a: usize, b: usize, max_len: Option<usize>, rng: &mut StdRng| {
+            let mut path = Vec::new();
+            for _ in 0..TUNNEL_ATTEMPTS {
+                let va = get_endpoint(&mut endpoints, &rooms, a, spacing, rng);
+                let vb = get_endpoint(&mut endpoints, &rooms, b, spacing, rng);
+                path = connector(va, vb, rng);
+                if sealed.is_some_and(|s| s == a || s == b) || !path.iter().any(|v| sealed_tiles.contains(v)) { break }
+                // the next roll starts from other spaced endpoints, without using these up
+                for idx in [a, b] {
+                    if let Some(points) = endpoints.get_mut(&idx) { points.rotate_right(1) }
+                }
+            }
+            if max_len.is_some_and(|m| path.len() > m) { return None }
+            // the spaced endpoints are only used up by the kept tunnels
+            for idx in [a, b] {
+                if let Some(points) = endpoints.get_mut(&idx) { points.pop(); }
+            }
+            Some(path)
+        };
+        (self.connections, self.paths) = self.connection_strategy.get_connections_generator()(&rooms, &connections, &mut joiner, rng, observer)
+            .into_iter()
+            .unzip();
         self.rooms = rooms;
     }
     // fn join_internal_rooms(&mut self, a: &Room, b: &Room, max_length: Option<u32>) {
@@ -68,69 +126,134 @@ impl Area {
     //     }
     //     self.paths.push(path);
     // }
-    fn get_closest_rooms<'a>(&'a self, other: &'a Area) -> (&'a Room, &'a Room) {
+    fn get_closest_rooms(&self, other: &Area) -> (usize, usize) {
         // find closest room pair between two areas
         // based on corner distances
+        // rooms behind a mission lock are only entered through it
+        let sealed_a = self.mission.as_ref().and_then(|m| m.get_locked_room());
+        let sealed_b = other.mission.as_ref().and_then(|m| m.get_locked_room());
         let mut dists = Vec::new();
-        for ra in self.rooms.iter() {
-            for rb in other.rooms.iter() {
-                // find min corner dist
-                let d = ra.corners().iter()
-                    .map(|ca| rb.corners().iter().map(|cb| ca.manhattan(*cb)).collect::<Vec<_>>())
-                    .flatten()
-                    .min()
-                    .unwrap();
-                dists.push((d, ra, rb));
+        for (ia, ra) in self.rooms.iter().enumerate() {
+            if Some(ia) == sealed_a { continue }
+            for (ib, rb) in other.rooms.iter().enumerate() {
+                if Some(ib) == sealed_b { continue }
+                dists.push((ra.corner_distance(rb), ia, ib));
             }
         }
-        dists.sort_by(|a, b| a.0.cmp(&b.0));
+        dists.sort_by_key(|a| a.0);
         (dists[0].1, dists[0].2)
     }
-    pub fn join(&self, other: &Area) -> Vec<Vector2Int> {
+    pub fn join(&self, other: &Area, rng: &mut StdRng) -> ((usize, usize), Vec<Vector2Int>) {
         // make a connection between two areas
-        let rooms = self.get_closest_rooms(other);
-        rooms.0.join(rooms.1, &self.tunneler)
+        // returns the joined room indexes (in self and other) and the path
+        let (a, b) = self.get_closest_rooms(other);
+        // rerolled when it runs into a room sealed by a mission lock
+        let sealed = [self, other].iter()
+            .filter_map(|area| Some(&area.rooms[area.mission.as_ref()?.get_locked_room()?]))
+            .flat_map(get_touching_tiles)
+            .collect::<HashSet<_>>();
+        let mut path = Vec::new();
+        for _ in 0..TUNNEL_ATTEMPTS {
+            path = self.rooms[a].join(&other.rooms[b], &self.tunneler, rng);
+            if !path.iter().any(|v| sealed.contains(v)) { break }
+        }
+        ((a, b), path)
     }
 }
 
-pub type ConnectionsGenerator<'a> = Box<dyn Fn(&Tunneler, &Vec<Room>, &Vec<(usize, usize)>) -> Vec<Vec<Vector2Int>> + 'a>;
+pub type ConnectionsGenerator<'a> = Box<
+    dyn Fn(&[Room], &[(usize, usize)], &mut RoomJoiner, &mut StdRng, &mut dyn FnMut(GenerationEvent)) -> Vec<Connection> + 'a
+>;
+// tunnels between two rooms of the area, given by their indexes
+// None when the tunnel is longer than the max length, it's not carved then
+pub type RoomJoiner<'a> = dyn FnMut(usize, usize, Option<usize>, &mut StdRng) -> Option<Vec<Vector2Int>> + 'a;
+// joined room indexes and the tunnel between them
+pub type Connection = ((usize, usize), Vec<Vector2Int>);
 
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ConnectionStrategy {
+    #[default]
     Basic,
-    Secondary(usize)
+    Secondary(usize),
+    // areas with less than 3 rooms fall back to basic
+    Cycle(CycleTemplate)
 }
 impl ConnectionStrategy {
-    pub fn get_connections_generator(&self) -> ConnectionsGenerator {
+    pub fn get_connections_generator(&self) -> ConnectionsGenerator<'_> {
         match self {
             Self::Basic => Box::new(
-                |tunneler: &Tunneler, rooms: &Vec<Room>, required: &Vec<(usize, usize)>| { get_neccessary_connections(tunneler, rooms, required) }
+                |_rooms: &[Room], required: &[(usize, usize)], joiner: &mut RoomJoiner, rng: &mut StdRng, observer: &mut dyn FnMut(GenerationEvent)| {
+                    get_neccessary_connections(required, joiner, rng, observer)
+                }
             ) as ConnectionsGenerator,
             Self::Secondary(max_dist) => Box::new(
-                |tunneler: &Tunneler, rooms: &Vec<Room>, required: &Vec<(usize, usize)>| { get_with_secondary(tunneler, rooms, required, *max_dist) }
+                |rooms: &[Room], required: &[(usize, usize)], joiner: &mut RoomJoiner, rng: &mut StdRng, observer: &mut dyn FnMut(GenerationEvent)| {
+                    get_with_secondary(rooms, required, *max_dist, joiner, rng, observer)
+                }
+            ) as ConnectionsGenerator,
+            Self::Cycle(_) => Box::new(
+                |_rooms: &[Room], required: &[(usize, usize)], joiner: &mut RoomJoiner, rng: &mut StdRng, observer: &mut dyn FnMut(GenerationEvent)| {
+                    get_neccessary_connections(required, joiner, rng, observer)
+                }
             ) as ConnectionsGenerator,
         }
     }
+    pub fn get_mission(&self, rooms: &mut [Room]) -> Option<MissionGraph> {
+        // builds the mission graph and embeds it into the rooms
+        let Self::Cycle(template) = self else { return None };
+        let mut mission = MissionGraph::new(*template, rooms.len())?;
+        mission.embed(rooms);
+        Some(mission)
+    }
+}
+
+fn get_touching_tiles(room: &Room) -> HashSet<Vector2Int> {
+    // the room's tiles and the ones next to it
+    room.get_tiles().iter()
+        .flat_map(|v| ORTHO_DIRECTIONS.iter().map(move |d| *v + *d).chain([*v]))
+        .collect()
+}
+
+fn get_endpoint(
+    endpoints: &mut HashMap<usize, Vec<Vector2Int>>, rooms: &[Room], idx: usize, spacing: Option<f32>, rng: &mut StdRng
+) -> Vector2Int {
+    match spacing {
+        None => rooms[idx].random_point(rng),
+        Some(radius) => {
+            // the next sampled point, it's popped by the joiner once its tunnel is kept
+            // resampled once the room runs out
+            let points = endpoints.entry(idx).or_default();
+            if points.is_empty() { *points = rooms[idx].sample_points(radius, 30, rng) }
+            points.last().copied().unwrap_or(rooms[idx].centre())
+        }
+    }
 }
 
 fn get_neccessary_connections(
-    tunneler: &Tunneler, rooms: &Vec<Room>, connections: &Vec<(usize, usize)>
-) -> Vec<Vec<Vector2Int>> {
+    connections: &[(usize, usize)], joiner: &mut RoomJoiner, rng: &mut StdRng,
+    observer: &mut dyn FnMut(GenerationEvent)
+) -> Vec<Connection> {
     connections.iter()
-        .map(|conn| rooms[conn.0].join(&rooms[conn.1], tunneler))
+        .filter_map(|conn| {
+            let path = joiner(conn.0, conn.1, None, rng)?;
+            observer(GenerationEvent::TunnelCarved(path.clone()));
+            Some((*conn, path))
+        })
         .collect()
 }
 
 fn get_with_secondary(
-    tunneler: &Tunneler, rooms: &Vec<Room>, required: &Vec<(usize, usize)>, max_dist: usize
-) -> Vec<Vec<Vector2Int>> {
-    let mut rng = thread_rng();
-    let mut paths = get_neccessary_connections(tunneler, rooms, required);
+    rooms: &[Room], required: &[(usize, usize)], max_dist: usize, joiner: &mut RoomJoiner, rng: &mut StdRng,
+    observer: &mut dyn FnMut(GenerationEvent)
+) -> Vec<Connection> {
+    let mut paths = get_neccessary_connections(required, joiner, rng, observer);
     for idx in 0..rooms.len() {
         let other_idx = rng.gen_range(0..rooms.len());
         if other_idx == idx { continue }
-        let path = rooms[idx].join(&rooms[other_idx], tunneler);
-        if path.len() > max_dist { continue };
-        paths.push(path);
+        let Some(path) = joiner(idx, other_idx, Some(max_dist), rng) else { continue };
+        observer(GenerationEvent::TunnelCarved(path.clone()));
+        paths.push(((idx, other_idx), path));
     };
     paths
 }
\ No newline at end of file
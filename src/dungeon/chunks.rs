@@ -0,0 +1,255 @@
+use rand::{prelude::*, rngs::StdRng};
+use std::collections::{HashMap, HashSet};
+
+use crate::vectors::{Vector2Int, ORTHO_DIRECTIONS};
+
+use super::{Area, ConnectionStrategy, Room, RoomGenerator, Tunneler};
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChunkSettings {
+    // width and height of a chunk, in tiles
+    pub size: i32,
+    pub room_generator: RoomGenerator,
+    pub tunneler: Tunneler,
+    pub connection_strategy: ConnectionStrategy,
+    // rolls of the room generator until the rooms fit in the chunk,
+    // a single room is used after that
+    pub attempts: usize
+}
+impl Default for ChunkSettings {
+    fn default() -> Self {
+        ChunkSettings {
+            size: 32,
+            room_generator: RoomGenerator::Grow { count: 4, min_size: 3, max_size: 6 },
+            tunneler: Tunneler::Weighted,
+            connection_strategy: ConnectionStrategy::Basic,
+            attempts: 10
+        }
+    }
+}
+impl ChunkSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.size < 8 { return Err("chunk size has to be at least 8".to_string()) }
+        self.room_generator.validate()
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Chunk {
+    pub coord: Vector2Int,
+    pub seed: u64,
+    // rooms and paths in world coordinates
+    pub area: Area,
+    // border tiles leading to the neighbours, in ORTHO_DIRECTIONS order
+    // the neighbour has its own portal on the tile right across
+    pub portals: Vec<Vector2Int>,
+    // tunnels from the portals into the rooms
+    pub portal_paths: Vec<Vec<Vector2Int>>,
+    pub tiles: HashSet<Vector2Int>
+}
+impl Chunk {
+    pub fn generate(world_seed: u64, coord: Vector2Int, settings: &ChunkSettings) -> Self {
+        // the same seed and coord always give the same chunk,
+        // no matter which chunks were generated before
+        let seed = get_hash(&[world_seed, coord.x as u64, coord.y as u64]);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let origin = coord * settings.size;
+        // rooms keep one tile off the border, so the portals stay free
+        let space = settings.size - 3;
+
+        let mut area = Area::new(
+            settings.room_generator.clone(),
+            settings.tunneler.clone(),
+            settings.connection_strategy.clone()
+        );
+        let mut fits = false;
+        for _ in 0..settings.attempts {
+            area.generate_rooms(&mut rng, &mut |_| ());
+            let size = area.get_size();
+            if size.x <= space && size.y <= space {
+                let slack = Vector2Int::new(rng.gen_range(0..=space - size.x), rng.gen_range(0..=space - size.y));
+                let base = origin + Vector2Int::new(1, 1) + slack;
+                area.shift(base.x, base.y);
+                fits = true;
+                break;
+            }
+        }
+        if !fits {
+            let c = origin + Vector2Int::new(settings.size / 2, settings.size / 2);
+            area.rooms = vec![Room::new(c - Vector2Int::new(1, 1), c + Vector2Int::new(1, 1))];
+            area.paths.clear();
+            area.connections.clear();
+            area.mission = None;
+        }
+
+        let connector = settings.tunneler.get_connector();
+        let portals = ORTHO_DIRECTIONS.iter()
+            .map(|d| get_portal(world_seed, coord, *d, settings.size))
+            .collect::<Vec<_>>();
+        let portal_paths = portals.iter().zip(ORTHO_DIRECTIONS)
+            .map(|(v, d)| {
+                // tunnel from the tile inside the portal, so no corridor runs along the border
+                let inner = *v - d;
+                let room = area.rooms.iter().min_by_key(|r| r.centre().manhattan(inner)).unwrap();
+                let mut path = connector(room.random_point(&mut rng), inner, &mut rng);
+                path.extend([inner, *v]);
+                path.dedup();
+                path
+            })
+            .collect::<Vec<_>>();
+
+        let mut tiles = HashSet::new();
+        for room in area.rooms.iter() {
+            tiles.extend(room.get_tiles());
+        }
+        tiles.extend(area.paths.iter().flatten());
+        tiles.extend(portal_paths.iter().flatten());
+        Chunk { coord, seed, area, portals, portal_paths, tiles }
+    }
+}
+
+// an endless grid of chunks, generated as they're requested
+pub struct ChunkedWorld {
+    pub seed: u64,
+    // private, so the settings can't be changed after they were validated
+    settings: ChunkSettings,
+    chunks: HashMap<Vector2Int, Chunk>
+}
+impl ChunkedWorld {
+    pub fn new(seed: u64, settings: ChunkSettings) -> Result<Self, String> {
+        settings.validate()?;
+        Ok(ChunkedWorld { seed, settings, chunks: HashMap::new() })
+    }
+    pub fn get_settings(&self) -> &ChunkSettings {
+        &self.settings
+    }
+    pub fn get_chunk(&mut self, coord: Vector2Int) -> &Chunk {
+        self.chunks.entry(coord)
+            .or_insert_with(|| Chunk::generate(self.seed, coord, &self.settings))
+    }
+    pub fn get_chunk_coord(&self, tile: Vector2Int) -> Vector2Int {
+        let size = self.settings.size;
+        Vector2Int::new(tile.x.div_euclid(size), tile.y.div_euclid(size))
+    }
+    pub fn load_around(&mut self, tile: Vector2Int, radius: i32) {
+        // generates the missing chunks in a square around the tile's chunk
+        let c = self.get_chunk_coord(tile);
+        for y in -radius..=radius {
+            for x in -radius..=radius {
+                self.get_chunk(c + Vector2Int::new(x, y));
+            }
+        }
+    }
+    pub fn unload(&mut self, coord: Vector2Int) -> Option<Chunk> {
+        // the chunk comes back the same when requested again
+        self.chunks.remove(&coord)
+    }
+    pub fn unload_outside(&mut self, tile: Vector2Int, radius: i32) {
+        let c = self.get_chunk_coord(tile);
+        self.chunks.retain(|k, _| (k.x - c.x).abs() <= radius && (k.y - c.y).abs() <= radius);
+    }
+    pub fn get_loaded(&self) -> impl Iterator<Item=&Chunk> {
+        self.chunks.values()
+    }
+    pub fn is_floor(&mut self, tile: Vector2Int) -> bool {
+        let coord = self.get_chunk_coord(tile);
+        self.get_chunk(coord).tiles.contains(&tile)
+    }
+    pub fn get_tiles(&self) -> HashSet<Vector2Int> {
+        // floor of all the loaded chunks
+        self.chunks.values().flat_map(|c| c.tiles.iter().copied()).collect()
+    }
+}
+
+fn get_portal(world_seed: u64, coord: Vector2Int, dir: Vector2Int, size: i32) -> Vector2Int {
+    // the position along the edge is hashed from the edge itself,
+    // so both chunks sharing it agree on the crossing
+    let (low, vertical) = match (dir.x, dir.y) {
+        (1, _) => (coord, true),
+        (-1, _) => (coord + dir, true),
+        (_, 1) => (coord, false),
+        _ => (coord + dir, false)
+    };
+    let hash = get_hash(&[world_seed, low.x as u64, low.y as u64, vertical as u64 + 1]);
+    // keep off the corners
+    let offset = 2 + (hash % (size - 4) as u64) as i32;
+    let origin = coord * size;
+    match (dir.x, dir.y) {
+        (1, _) => origin + Vector2Int::new(size - 1, offset),
+        (-1, _) => origin + Vector2Int::new(0, offset),
+        (_, 1) => origin + Vector2Int::new(offset, size - 1),
+        _ => origin + Vector2Int::new(offset, 0)
+    }
+}
+
+fn get_hash(values: &[u64]) -> u64 {
+    // splitmix64 over the values, stable across platforms and releases
+    values.iter().fold(0x9E3779B97F4A7C15, |h, v| {
+        let mut z = (h ^ *v).wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_chunks_are_rejected() {
+        for size in [-1, 0, 4, 7] {
+            let settings = ChunkSettings { size, ..Default::default() };
+            assert!(ChunkedWorld::new(1, settings).is_err());
+        }
+        let settings = ChunkSettings { size: 8, ..Default::default() };
+        let mut world = ChunkedWorld::new(1, settings).unwrap();
+        world.load_around(Vector2Int::new(0, 0), 1);
+        for chunk in world.get_loaded() {
+            let origin = chunk.coord * 8;
+            assert!(chunk.tiles.iter().all(|v| {
+                v.x >= origin.x && v.x < origin.x + 8 && v.y >= origin.y && v.y < origin.y + 8
+            }));
+        }
+    }
+
+    #[test]
+    fn chunks_are_deterministic() {
+        let settings = ChunkSettings::default();
+        let mut world = ChunkedWorld::new(7, settings.clone()).unwrap();
+        // neighbours loaded first, the chunk can't depend on them
+        world.load_around(Vector2Int::new(40, 40), 1);
+        let coord = Vector2Int::new(1, 1);
+        world.unload(coord);
+        for coord in [coord, Vector2Int::new(-3, 2)] {
+            let a = Chunk::generate(7, coord, &settings);
+            let b = world.get_chunk(coord);
+            assert_eq!(a.seed, b.seed);
+            assert_eq!(a.tiles, b.tiles);
+            assert_eq!(a.portals, b.portals);
+            assert_eq!(a.portal_paths, b.portal_paths);
+            assert_eq!(a.area.paths, b.area.paths);
+        }
+    }
+
+    #[test]
+    fn portals_line_up_across_borders() {
+        let mut world = ChunkedWorld::new(3, ChunkSettings::default()).unwrap();
+        world.load_around(Vector2Int::new(0, 0), 2);
+        for y in -1..=1 {
+            for x in -1..=1 {
+                let coord = Vector2Int::new(x, y);
+                let portals = world.get_chunk(coord).portals.clone();
+                for (i, d) in ORTHO_DIRECTIONS.iter().enumerate() {
+                    let other = world.get_chunk(coord + *d);
+                    // the neighbour's portal back to this chunk
+                    let back = ORTHO_DIRECTIONS.iter().position(|o| *o == Vector2Int::new(-d.x, -d.y)).unwrap();
+                    assert_eq!(portals[i] + *d, other.portals[back]);
+                    assert!(other.tiles.contains(&(portals[i] + *d)));
+                    assert!(world.is_floor(portals[i]));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use super::room::Room;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CycleTemplate {
+    // two open routes of similar length between the start and the goal
+    AlternatePaths,
+    // the short route is locked, the long one leads to its key and ends there
+    LockKey,
+    // the short route is a secret passage, found from the goal side
+    HiddenShortcut
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MissionRole {
+    Start,
+    Goal,
+    Key,
+    Path
+}
+impl MissionRole {
+    pub fn get_name(&self) -> &str {
+        match self {
+            Self::Start => "start",
+            Self::Goal => "goal",
+            Self::Key => "key",
+            Self::Path => "path"
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EdgeKind {
+    Normal,
+    Locked,
+    Hidden
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MissionEdge {
+    pub a: usize,
+    pub b: usize,
+    pub kind: EdgeKind
+}
+
+// abstract cycle of nodes, optionally embedded into an area's rooms
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MissionGraph {
+    pub template: CycleTemplate,
+    pub roles: Vec<MissionRole>,
+    pub edges: Vec<MissionEdge>,
+    // room index per node, empty until embedded
+    pub rooms: Vec<usize>
+}
+impl MissionGraph {
+    pub fn new(template: CycleTemplate, size: usize) -> Option<Self> {
+        // a ring of nodes, the start being node 0
+        // needs at least 3 nodes to form a cycle
+        // for the lock-key the ring is cut between the goal and the key,
+        // so the goal can only be entered through the lock
+        if size < 3 { return None }
+        let goal = match template {
+            CycleTemplate::AlternatePaths => size / 2,
+            // one third of the way, so the second route is the long one
+            CycleTemplate::LockKey | CycleTemplate::HiddenShortcut => (size / 3).max(1)
+        };
+
+        let mut roles = vec![MissionRole::Path; size];
+        roles[0] = MissionRole::Start;
+        roles[goal] = MissionRole::Goal;
+        if template == CycleTemplate::LockKey {
+            // end of the long route, right next to the goal
+            roles[goal + 1] = MissionRole::Key;
+        }
+
+        let edges = (0..size)
+            .filter(|i| template != CycleTemplate::LockKey || *i != goal)
+            .map(|i| {
+                let b = (i + 1) % size;
+                // the last edge of the short route, leading into the goal
+                let kind = match template {
+                    CycleTemplate::LockKey if b == goal => EdgeKind::Locked,
+                    CycleTemplate::HiddenShortcut if b == goal => EdgeKind::Hidden,
+                    _ => EdgeKind::Normal
+                };
+                MissionEdge { a: i, b, kind }
+            })
+            .collect();
+        Some(MissionGraph { template, roles, edges, rooms: Vec::new() })
+    }
+    pub fn embed(&mut self, rooms: &mut [Room]) {
+        // map the ring onto the rooms, ordered by angle around their middle,
+        // so the tunnels go round instead of crossing the area
+        // the first room stays the start
+        let n = rooms.len() as f32;
+        let cx = rooms.iter().map(|r| r.centre().x as f32).sum::<f32>() / n;
+        let cy = rooms.iter().map(|r| r.centre().y as f32).sum::<f32>() / n;
+        let angle = |r: &Room| (r.centre().y as f32 - cy).atan2(r.centre().x as f32 - cx);
+
+        let mut order = (0..rooms.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| angle(&rooms[*a]).total_cmp(&angle(&rooms[*b])));
+        let start = order.iter().position(|i| *i == 0).unwrap_or(0);
+        order.rotate_left(start);
+        order.truncate(self.roles.len());
+
+        for (node, room) in order.iter().enumerate() {
+            rooms[*room].properties.insert("cycle".to_string(), self.roles[node].get_name().to_string());
+        }
+        self.rooms = order;
+    }
+    pub fn get_room(&self, role: MissionRole) -> Option<usize> {
+        // room index of the first node with the role, once embedded
+        let node = self.roles.iter().position(|r| *r == role)?;
+        self.rooms.get(node).copied()
+    }
+    pub fn get_locked_room(&self) -> Option<usize> {
+        // room entered through the locked edge, once embedded
+        let edge = self.edges.iter().find(|e| e.kind == EdgeKind::Locked)?;
+        self.rooms.get(edge.b).copied()
+    }
+    pub fn get_connections(&self) -> Vec<(usize, usize)> {
+        // room index pairs, in the same order as the edges
+        self.edges.iter()
+            .map(|e| (self.rooms[e.a], self.rooms[e.b]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use crate::dungeon::{Area, ConnectionStrategy, Dungeon, RoomGenerator, Tunneler};
+    use crate::vectors::find_path;
+    use super::*;
+
+    fn get_reached(mission: &MissionGraph, skip_locked: bool) -> HashSet<usize> {
+        // nodes walked to from the start over the mission edges
+        let mut reached = HashSet::from([0]);
+        let mut stack = vec![0];
+        while let Some(cur) = stack.pop() {
+            for edge in mission.edges.iter() {
+                if skip_locked && edge.kind == EdgeKind::Locked { continue }
+                let n = match (edge.a == cur, edge.b == cur) {
+                    (true, _) => edge.b,
+                    (_, true) => edge.a,
+                    _ => continue
+                };
+                if reached.insert(n) { stack.push(n) }
+            }
+        }
+        reached
+    }
+
+    #[test]
+    fn lock_key_goal_needs_the_key() {
+        for size in 3..12 {
+            let mission = MissionGraph::new(CycleTemplate::LockKey, size).unwrap();
+            let goal = mission.roles.iter().position(|r| *r == MissionRole::Goal).unwrap();
+            let key = mission.roles.iter().position(|r| *r == MissionRole::Key).unwrap();
+            let open = get_reached(&mission, true);
+            assert!(!open.contains(&goal), "size {}", size);
+            assert!(open.contains(&key), "size {}", size);
+            // the long route ends at the key
+            assert_eq!(mission.edges.iter().filter(|e| e.a == key || e.b == key).count(), 1);
+            assert_eq!(get_reached(&mission, false).len(), size);
+        }
+    }
+
+    fn get_dungeon(seed: u64, template: CycleTemplate) -> Dungeon {
+        let mut d = Dungeon::new();
+        d.seed = seed;
+        for strategy in [ConnectionStrategy::Cycle(template), ConnectionStrategy::Basic] {
+            d.add_area(Area::new(
+                RoomGenerator::GrowSeparated { count: 7, min_size: 3, max_size: 5 },
+                Tunneler::Weighted,
+                strategy
+            ));
+        }
+        d.generate();
+        d
+    }
+
+    #[test]
+    fn mission_lock_blocks_the_goal() {
+        let mut placed = 0;
+        for seed in 0..40 {
+            let mut d = get_dungeon(seed, CycleTemplate::LockKey);
+            let mission = d.areas[0].mission.clone().unwrap();
+            let start = d.get_room((0, mission.rooms[0])).centre();
+            let goal = d.get_room((0, mission.get_locked_room().unwrap())).centre();
+            let Some(lock) = d.locks.first().cloned() else {
+                // only skipped when the goal can be reached without the locked corridor
+                let edge = mission.edges.iter().position(|e| e.kind == EdgeKind::Locked).unwrap();
+                let corridor = d.graph.edges[edge].path.iter()
+                    .filter(|v| d.get_rooms().all(|(_, r)| !r.get_tiles().contains(v)))
+                    .copied()
+                    .collect::<HashSet<_>>();
+                assert!(find_path(start, goal, &d.tiles, &corridor).is_some(), "seed {}", seed);
+                assert_eq!(d.get_skipped_missions(), vec![0]);
+                continue
+            };
+            placed += 1;
+            assert!(d.get_skipped_missions().is_empty());
+            let key = d.keys.iter().find(|k| k.lock == 0).unwrap();
+            assert_eq!(lock.room.1, mission.get_locked_room().unwrap());
+            assert_eq!(key.room.1, mission.get_room(MissionRole::Key).unwrap());
+
+            let closed = HashSet::from([lock.tile]);
+            assert!(find_path(start, goal, &d.tiles, &closed).is_none(), "seed {}", seed);
+            assert!(find_path(start, key.tile, &d.tiles, &closed).is_some(), "seed {}", seed);
+            assert!(d.is_solvable(start, &d.locks, &d.keys));
+            // the stairs leave the goal behind its lock
+            d.place_stairs(1.);
+            assert!(!d.get_room(lock.room).get_tiles().contains(&d.entrance.unwrap()), "seed {}", seed);
+            assert!(d.is_solvable(d.entrance.unwrap(), &d.locks, &d.keys), "seed {}", seed);
+            // a key behind its own lock can't be picked up
+            let mut moved = d.keys.clone();
+            moved[0].tile = goal;
+            assert!(!d.is_solvable(start, &d.locks, &moved));
+        }
+        // other tunnels are kept out of the goal room, so skips are rare
+        assert!(placed >= 38, "{} locks placed", placed);
+    }
+
+    #[test]
+    fn hidden_shortcut_is_marked() {
+        for seed in 0..10 {
+            let d = get_dungeon(seed, CycleTemplate::HiddenShortcut);
+            let hidden = d.graph.edges.iter().filter(|c| c.hidden).collect::<Vec<_>>();
+            assert_eq!(hidden.len(), 1);
+            let mission = d.areas[0].mission.as_ref().unwrap();
+            let goal = mission.rooms[mission.roles.iter().position(|r| *r == MissionRole::Goal).unwrap()];
+            assert!(hidden[0].a == (0, goal) || hidden[0].b == (0, goal));
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use crate::vectors::get_distances;
+
+use super::{Dungeon, RoomRef};
+
+// difficulty range of an area, its rooms are spread over it by depth
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DifficultyBand {
+    pub min: f32,
+    pub max: f32,
+    // 1. replaces the room depth with the band, 0. ignores the band
+    #[cfg_attr(feature = "serde", serde(default = "default_blend"))]
+    pub blend: f32
+}
+impl DifficultyBand {
+    pub fn new(min: f32, max: f32) -> Self {
+        DifficultyBand { min, max, blend: 1. }
+    }
+    pub fn validate(&self) -> Result<(), String> {
+        let valid = |v: f32| (0. ..=1.).contains(&v);
+        if !valid(self.min) || !valid(self.max) || self.min > self.max {
+            return Err("difficulty band has to be within 0..1, with min not above max".to_string())
+        }
+        if !valid(self.blend) {
+            return Err("difficulty blend has to be within 0..1".to_string())
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+fn default_blend() -> f32 { 1. }
+
+impl Dungeon {
+    pub fn get_room_depths(&self) -> HashMap<RoomRef, f32> {
+        // walking distance from the entrance to the room centres, scaled to 0..1
+        // starts from the first room when there's no entrance
+        // rooms that can't be reached are the deepest
+        let Some(start) = self.entrance.or(self.get_rooms().next().map(|(_, r)| r.centre())) else {
+            return HashMap::new()
+        };
+        let distances = get_distances(start, &self.tiles);
+        let max = self.get_rooms()
+            .filter_map(|(_, r)| distances.get(&r.centre()))
+            .max()
+            .copied()
+            .unwrap_or(0)
+            .max(1);
+        self.get_rooms()
+            .map(|(r, room)| {
+                let depth = distances.get(&room.centre()).map_or(1., |d| *d as f32 / max as f32);
+                (r, depth)
+            })
+            .collect()
+    }
+    pub fn get_difficulties(&self) -> HashMap<RoomRef, f32> {
+        // room depths, blended with the difficulty bands of their areas
+        let mut difficulties = self.get_room_depths();
+        for (i, area) in self.areas.iter().enumerate() {
+            let Some(band) = &area.difficulty else { continue };
+            let depths = (0..area.rooms.len()).map(|r| difficulties[&(i, r)]).collect::<Vec<_>>();
+            let min = depths.iter().copied().fold(f32::MAX, f32::min);
+            let max = depths.iter().copied().fold(f32::MIN, f32::max);
+            for (r, depth) in depths.iter().enumerate() {
+                // position within the area, the middle of the band for a single room
+                let t = if max > min { (depth - min) / (max - min) } else { 0.5 };
+                let value = band.min + (band.max - band.min) * t;
+                difficulties.insert((i, r), depth + (value - depth) * band.blend);
+            }
+        }
+        difficulties
+    }
+}
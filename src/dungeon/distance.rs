@@ -0,0 +1,28 @@
+use std::collections::HashMap;
+
+use crate::vectors::{get_cost_map, Grid, Vector2Int};
+
+use super::Dungeon;
+
+// walking cost to the nearest goal, None for unreachable or empty tiles
+pub type DistanceMap = Grid<Option<u32>>;
+
+impl Dungeon {
+    pub fn get_distance_map(
+        &self,
+        goals: &[Vector2Int],
+        costs: Option<&HashMap<Vector2Int, u32>>
+    ) -> DistanceMap {
+        // tiles missing from the cost table have a default cost of 1
+        let costs = get_cost_map(goals, &self.tiles, |v| match costs {
+            Some(c) => *c.get(&v).unwrap_or(&1),
+            None => 1
+        });
+        let bounds = self.get_padded_bounds(0);
+        let mut map = Grid::from_bounds(bounds.0, bounds.1, None);
+        for (v, c) in costs {
+            map.set(v, Some(c));
+        }
+        map
+    }
+}
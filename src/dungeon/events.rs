@@ -0,0 +1,27 @@
+use crate::vectors::Vector2Int;
+
+use super::{Dungeon, Room};
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GenerationEvent {
+    // room and tunnel events that follow are in this area's local coordinates,
+    // until the area gets shifted
+    AreaStarted(usize),
+    RoomPlaced(Room),
+    // a candidate room that overlapped one of the existing rooms
+    RoomRejected(Room),
+    TunnelCarved(Vec<Vector2Int>),
+    AreaShifted { area: usize, offset: Vector2Int },
+    // the path is already in the dungeon's coordinates
+    AreasConnected { from: usize, to: usize, path: Vec<Vector2Int> }
+}
+
+impl Dungeon {
+    pub fn record_generation(&mut self) -> Vec<GenerationEvent> {
+        // generate the dungeon, keeping every step
+        let mut events = Vec::new();
+        self.generate_with(&mut |e| events.push(e));
+        events
+    }
+}
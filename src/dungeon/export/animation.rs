@@ -0,0 +1,122 @@
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, ImageResult, Rgba, RgbaImage
+};
+use std::{collections::HashMap, fs::File, path::Path};
+
+use crate::vectors::Vector2Int;
+
+use super::super::{Dungeon, GenerationEvent};
+use super::render::{fill_tile, Colour};
+
+pub struct AnimationSettings {
+    // size of a single tile in pixels
+    pub scale: u32,
+    pub background: Colour,
+    pub floor: Colour,
+    // colour of the latest step, before it becomes floor
+    pub highlight: Colour,
+    // rejected rooms are outlined for a single frame, skipped when None
+    pub rejected: Option<Colour>,
+    // frame delay in milliseconds
+    pub delay: u32,
+    // how long the finished dungeon stays on screen
+    pub hold: u32
+}
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        AnimationSettings {
+            scale: 8,
+            background: [0, 0, 0, 255],
+            floor: [150, 150, 50, 255],
+            highlight: [240, 220, 120, 255],
+            rejected: Some([200, 60, 60, 255]),
+            delay: 150,
+            hold: 2000
+        }
+    }
+}
+
+impl Dungeon {
+    pub fn render_frames(&self, events: &[GenerationEvent], settings: &AnimationSettings)
+    -> Vec<(RgbaImage, u32)> {
+        // returns the frames with their delays in ms
+        // room events are drawn at their area's final position
+        let offsets = events.iter()
+            .filter_map(|e| match e {
+                GenerationEvent::AreaShifted { area, offset } => Some((*area, *offset)),
+                _ => None
+            })
+            .collect::<HashMap<_, _>>();
+
+        let (a, b) = self.get_padded_bounds(0);
+        let scale = settings.scale.max(1);
+        let w = (b.x - a.x + 1) as u32;
+        let h = (b.y - a.y + 1) as u32;
+        let mut canvas = RgbaImage::from_pixel(w * scale, h * scale, Rgba(settings.background));
+
+        let paint = |buf: &mut RgbaImage, tiles: &[Vector2Int], colour: Colour| {
+            for v in tiles {
+                let t = *v - a;
+                // rejected rooms can stick out of the final bounds
+                if t.x < 0 || t.y < 0 || t.x >= w as i32 || t.y >= h as i32 { continue }
+                fill_tile(buf, t.x as u32, t.y as u32, scale, colour);
+            }
+        };
+
+        let mut frames = Vec::new();
+        let mut offset = Vector2Int::new(0, 0);
+        for event in events {
+            let (tiles, colour, commit) = match event {
+                GenerationEvent::AreaStarted(idx) => {
+                    offset = *offsets.get(idx).unwrap_or(&Vector2Int::new(0, 0));
+                    continue
+                },
+                GenerationEvent::RoomPlaced(room) => (
+                    room.get_tiles().into_iter().map(|v| v + offset).collect::<Vec<_>>(),
+                    settings.highlight,
+                    true
+                ),
+                GenerationEvent::RoomRejected(room) => {
+                    let Some(colour) = settings.rejected else { continue };
+                    let outline = room.get_tiles().into_iter()
+                        .filter(|v| v.x == room.a.x || v.x == room.b.x || v.y == room.a.y || v.y == room.b.y)
+                        .map(|v| v + offset)
+                        .collect::<Vec<_>>();
+                    (outline, colour, false)
+                },
+                GenerationEvent::TunnelCarved(path) => (
+                    path.iter().map(|v| *v + offset).collect(),
+                    settings.highlight,
+                    true
+                ),
+                GenerationEvent::AreasConnected { path, .. } => (path.clone(), settings.highlight, true),
+                GenerationEvent::AreaShifted { .. } => continue
+            };
+            let mut frame = canvas.clone();
+            paint(&mut frame, &tiles, colour);
+            frames.push((frame, settings.delay));
+            if commit { paint(&mut canvas, &tiles, settings.floor) }
+        }
+        frames.push((canvas, settings.hold));
+        frames
+    }
+    pub fn save_gif(&self, path: &str, events: &[GenerationEvent], settings: &AnimationSettings)
+    -> ImageResult<()> {
+        let mut encoder = GifEncoder::new_with_speed(File::create(path)?, 10);
+        encoder.set_repeat(Repeat::Infinite)?;
+        encoder.encode_frames(
+            self.render_frames(events, settings).into_iter()
+                .map(|(img, ms)| Frame::from_parts(img, 0, 0, Delay::from_numer_denom_ms(ms, 1)))
+        )
+    }
+    pub fn save_frames(&self, dir: &str, events: &[GenerationEvent], settings: &AnimationSettings)
+    -> ImageResult<()> {
+        // writes numbered png frames, eg. dir/frame_0042.png
+        std::fs::create_dir_all(dir)?;
+        for (i, (img, _)) in self.render_frames(events, settings).iter().enumerate() {
+            img.save(Path::new(dir).join(format!("frame_{:04}.png", i)))?;
+        }
+        Ok(())
+    }
+}
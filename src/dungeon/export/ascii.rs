@@ -0,0 +1,102 @@
+use crate::vectors::Vector2Int;
+
+use super::super::{Dungeon, EntityKind, TileKind};
+
+const ROOM_LABELS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+// ansi foreground colours used for the areas
+const AREA_COLOURS: [u8; 12] = [31, 32, 33, 34, 35, 36, 91, 92, 93, 94, 95, 96];
+
+#[derive(Default)]
+pub struct AsciiSettings {
+    // label room centres with their index (0-9, a-z, A-Z, then repeating)
+    pub room_ids: bool,
+    // colour tiles by their area using ansi escape codes
+    pub colour: bool
+}
+
+impl Dungeon {
+    pub fn to_ascii(&self, settings: &AsciiSettings) -> String {
+        let map = self.get_tile_map();
+        let areas = self.get_area_map();
+
+        let mut labels: Vec<Option<char>> = vec![None; map.values.len()];
+        let mut set_label = |v: Vector2Int, c: char| {
+            // labels off the map are dropped, instead of wrapping into the next row
+            let d = v - map.origin;
+            if d.x < 0 || d.y < 0 || d.x as usize >= map.width || d.y as usize >= map.height { return }
+            labels[d.y as usize * map.width + d.x as usize] = Some(c);
+        };
+        if settings.room_ids {
+            for (i, (_, room)) in self.get_rooms().enumerate() {
+                set_label(room.centre(), ROOM_LABELS[i % ROOM_LABELS.len()] as char);
+            }
+        }
+        for p in self.placements.iter() {
+            set_label(p.tile, match p.kind {
+                EntityKind::Monster => 'M',
+                EntityKind::Loot => '$',
+                EntityKind::Trap => '^',
+                EntityKind::Prop => '%'
+            });
+        }
+        for lock in self.locks.iter() { set_label(lock.tile, '=') }
+        for key in self.keys.iter() { set_label(key.tile, '*') }
+        if let Some(v) = self.entrance { set_label(v, '<') }
+        if let Some(v) = self.exit { set_label(v, '>') }
+
+        let mut out = String::new();
+        for y in 0..map.height {
+            let mut colour = None;
+            for x in 0..map.width {
+                let idx = y * map.width + x;
+                let v = map.origin + Vector2Int::new(x as i32, y as i32);
+                let c = labels[idx].unwrap_or(match map.values[idx] {
+                    TileKind::Empty => ' ',
+                    TileKind::Wall => '#',
+                    TileKind::Room | TileKind::Corridor => '.',
+                    TileKind::Door => '+'
+                });
+                if settings.colour {
+                    let cur = areas.get(v).copied().flatten()
+                        .map(|a| AREA_COLOURS[a % AREA_COLOURS.len()]);
+                    // only emit escape codes when the colour changes
+                    if cur != colour {
+                        match cur {
+                            Some(code) => out.push_str(&format!("\x1b[{}m", code)),
+                            None => out.push_str("\x1b[0m")
+                        }
+                        colour = cur;
+                    }
+                }
+                out.push(c);
+            }
+            if colour.is_some() { out.push_str("\x1b[0m") }
+            out.push('\n');
+        }
+        out
+    }
+    pub fn save_ascii(&self, path: &str, settings: &AsciiSettings) -> std::io::Result<()> {
+        std::fs::write(path, self.to_ascii(settings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_stay_on_their_row() {
+        let mut d = Dungeon::new();
+        d.tiles.extend((0..4).map(|x| Vector2Int::new(x, 0)));
+        d.tiles.insert(Vector2Int::new(0, 1));
+        // the right edge of the padded map, and two tiles off it
+        d.exit = Some(Vector2Int::new(4, 0));
+        d.entrance = Some(Vector2Int::new(6, 1));
+        let out = d.to_ascii(&AsciiSettings::default());
+        let rows = out.lines().collect::<Vec<_>>();
+        assert_eq!(rows.len(), 4);
+        assert!(rows.iter().all(|r| r.chars().count() == 6));
+        assert_eq!(rows[1], "#....>");
+        assert!(!out.contains('<'));
+    }
+}
@@ -0,0 +1,476 @@
+use serde_json::{json, Value};
+use std::cell::Cell;
+
+use crate::vectors::{Grid, Vector2Int};
+
+use super::super::{Dungeon, EntityKind, TileKind};
+
+// layer / entity / field definition uids
+const TILES_LAYER_UID: u32 = 1;
+const ENTITIES_LAYER_UID: u32 = 2;
+const ROOM_ENTITY_UID: u32 = 3;
+const DOOR_ENTITY_UID: u32 = 4;
+const START_ENTITY_UID: u32 = 5;
+const EXIT_ENTITY_UID: u32 = 6;
+const AREA_FIELD_UID: u32 = 7;
+const ROOM_FIELD_UID: u32 = 8;
+const KIND_FIELD_UID: u32 = 9;
+const LOCK_ENTITY_UID: u32 = 10;
+const KEY_ENTITY_UID: u32 = 11;
+const LOCK_FIELD_UID: u32 = 12;
+const KEY_LOCK_FIELD_UID: u32 = 13;
+const FIRST_LEVEL_UID: u32 = 100;
+
+// placed entities get a definition per kind, with its own name field
+// (kind, identifier, entity uid, name field uid, colour)
+const PLACEMENT_DEFS: [(EntityKind, &str, u32, u32, &str); 4] = [
+    (EntityKind::Monster, "Monster", 14, 15, "#E84FB4"),
+    (EntityKind::Loot, "Loot", 16, 17, "#E8D84F"),
+    (EntityKind::Trap, "Trap", 18, 19, "#B44FE8"),
+    (EntityKind::Prop, "Prop", 20, 21, "#8C8C8C")
+];
+
+// IntGrid values
+const FLOOR_VALUE: u8 = 1;
+const WALL_VALUE: u8 = 2;
+const CORRIDOR_VALUE: u8 = 3;
+
+const LDTK_VERSION: &str = "1.5.3";
+
+pub struct LdtkSettings {
+    pub grid_size: u32,
+    // export every area as a separate level
+    // (corridors running between the areas are left out)
+    pub level_per_area: bool
+}
+impl Default for LdtkSettings {
+    fn default() -> Self {
+        LdtkSettings { grid_size: 16, level_per_area: false }
+    }
+}
+
+impl Dungeon {
+    pub fn to_ldtk(&self, settings: &LdtkSettings) -> String {
+        let map = self.get_tile_map();
+        let iids = IidGenerator::default();
+        let margin = Vector2Int::new(1, 1);
+        let gs = settings.grid_size;
+
+        let contexts = match settings.level_per_area {
+            false => vec![LevelContext {
+                identifier: "Level_0".to_string(),
+                uid: FIRST_LEVEL_UID,
+                bounds: (map.origin, map.origin + Vector2Int::new(map.width as i32 - 1, map.height as i32 - 1)),
+                area: None,
+                grid_size: gs as i32,
+                iids: &iids
+            }],
+            true => self.areas.iter().enumerate()
+                // areas get their rooms on generation
+                .filter(|(_, area)| !area.rooms.is_empty())
+                .map(|(i, area)| {
+                    let bounds = area.get_bounds();
+                    LevelContext {
+                        identifier: format!("Area_{}", i),
+                        uid: FIRST_LEVEL_UID + i as u32,
+                        bounds: (bounds.0 - margin, bounds.1 + margin),
+                        area: Some(i),
+                        grid_size: gs as i32,
+                        iids: &iids
+                    }
+                })
+                .collect()
+        };
+        // skipped areas leave gaps in the level uids
+        let next_uid = contexts.iter().map(|c| c.uid + 1).max().unwrap_or(FIRST_LEVEL_UID);
+        let levels = contexts.iter().map(|c| self.get_ldtk_level(c, &map)).collect::<Vec<_>>();
+
+        let int_grid_values = [
+            (FLOOR_VALUE, "floor", "#C8C8A0"),
+            (WALL_VALUE, "wall", "#32323C"),
+            (CORRIDOR_VALUE, "corridor", "#96963C")
+        ].iter()
+            .map(|(v, id, c)| json!({ "value": v, "identifier": id, "color": c, "tile": null, "groupUid": 0 }))
+            .collect::<Vec<_>>();
+        let mut entities = vec![
+            get_entity_def("Room", ROOM_ENTITY_UID, gs, "#4FB4E8", true, &[
+                ("area", AREA_FIELD_UID, "Int"),
+                ("room", ROOM_FIELD_UID, "Int"),
+                ("kind", KIND_FIELD_UID, "String")
+            ]),
+            get_entity_def("Door", DOOR_ENTITY_UID, gs, "#E8A84F", false, &[]),
+            get_entity_def("Start", START_ENTITY_UID, gs, "#4FE85A", false, &[]),
+            get_entity_def("Exit", EXIT_ENTITY_UID, gs, "#E84F4F", false, &[]),
+            get_entity_def("Lock", LOCK_ENTITY_UID, gs, "#C03030", false, &[("lock", LOCK_FIELD_UID, "Int")]),
+            get_entity_def("Key", KEY_ENTITY_UID, gs, "#E8C84F", false, &[("lock", KEY_LOCK_FIELD_UID, "Int")])
+        ];
+        entities.extend(PLACEMENT_DEFS.iter().map(|(_, id, uid, field_uid, colour)|
+            get_entity_def(id, *uid, gs, colour, false, &[("name", *field_uid, "String")])
+        ));
+
+        let project = json!({
+            "__header__": {
+                "fileType": "LDtk Project JSON",
+                "app": "LDtk",
+                "doc": "https://ldtk.io/json",
+                "schema": "https://ldtk.io/files/JSON_SCHEMA.json",
+                "appAuthor": "Sebastien 'deepnight' Benard",
+                "appVersion": LDTK_VERSION,
+                "url": "https://ldtk.io"
+            },
+            "iid": iids.next(),
+            "jsonVersion": LDTK_VERSION,
+            "appBuildId": 0,
+            "nextUid": next_uid,
+            "identifierStyle": "Capitalize",
+            "toc": [],
+            "worldLayout": "Free",
+            "worldGridWidth": gs,
+            "worldGridHeight": gs,
+            "defaultLevelWidth": map.width as u32 * gs,
+            "defaultLevelHeight": map.height as u32 * gs,
+            "defaultPivotX": 0,
+            "defaultPivotY": 0,
+            "defaultGridSize": gs,
+            "defaultEntityWidth": gs,
+            "defaultEntityHeight": gs,
+            "bgColor": "#40465B",
+            "defaultLevelBgColor": "#696A79",
+            "minifyJson": false,
+            "externalLevels": false,
+            "exportTiled": false,
+            "simplifiedExport": false,
+            "imageExportMode": "None",
+            "exportLevelBg": true,
+            "pngFilePattern": null,
+            "backupOnSave": false,
+            "backupLimit": 10,
+            "backupRelPath": null,
+            "levelNamePattern": "Level_%idx",
+            "tutorialDesc": null,
+            "customCommands": [],
+            "flags": [],
+            "defs": {
+                "layers": [
+                    get_layer_def("Entities", "Entities", ENTITIES_LAYER_UID, gs, Vec::new()),
+                    get_layer_def("Tiles", "IntGrid", TILES_LAYER_UID, gs, int_grid_values)
+                ],
+                "entities": entities,
+                "tilesets": [],
+                "enums": [],
+                "externalEnums": [],
+                "levelFields": []
+            },
+            "levels": levels,
+            "worlds": [],
+            "dummyWorldIid": iids.next()
+        });
+
+        let mut out = serde_json::to_string_pretty(&project).unwrap_or_default();
+        out.push('\n');
+        out
+    }
+    pub fn save_ldtk(&self, path: &str, settings: &LdtkSettings) -> std::io::Result<()> {
+        std::fs::write(path, self.to_ldtk(settings))
+    }
+    fn get_ldtk_level(&self, ctx: &LevelContext, map: &Grid<TileKind>) -> Value {
+        let (bounds, gs) = (ctx.bounds, ctx.grid_size);
+        let (c_wid, c_hei) = ctx.get_size();
+        let contains = |v: Vector2Int| v.x >= bounds.0.x && v.x <= bounds.1.x && v.y >= bounds.0.y && v.y <= bounds.1.y;
+        let world = ctx.get_world();
+
+        let csv = (bounds.0.y..=bounds.1.y)
+            .flat_map(|y| (bounds.0.x..=bounds.1.x).map(move |x| Vector2Int::new(x, y)))
+            .map(|v| match map.get(v) {
+                Some(TileKind::Room) => FLOOR_VALUE,
+                Some(TileKind::Corridor) | Some(TileKind::Door) => CORRIDOR_VALUE,
+                Some(TileKind::Wall) => WALL_VALUE,
+                _ => 0
+            })
+            .collect::<Vec<_>>();
+
+        let mut entities = self.get_rooms()
+            .filter(|(r, _)| ctx.area.is_none_or(|a| a == r.0))
+            .map(|((area_idx, room_idx), room)| {
+                let fields = vec![
+                    get_field("area", AREA_FIELD_UID, "Int", json!(area_idx)),
+                    get_field("room", ROOM_FIELD_UID, "Int", json!(room_idx)),
+                    get_field("kind", KIND_FIELD_UID, "String", json!(room.kind.get_name()))
+                ];
+                let size = (room.b - room.a + Vector2Int::new(1, 1)) * gs;
+                ctx.get_entity_instance("Room", ROOM_ENTITY_UID, room.a, size, fields)
+            })
+            .collect::<Vec<_>>();
+
+        // single tile entities, locks and keys reference each other by the lock index
+        let points = self.get_doors().into_iter().map(|v| ("Door", DOOR_ENTITY_UID, v, Vec::new()))
+            .chain(self.entrance.map(|v| ("Start", START_ENTITY_UID, v, Vec::new())))
+            .chain(self.exit.map(|v| ("Exit", EXIT_ENTITY_UID, v, Vec::new())))
+            .chain(self.locks.iter().enumerate().map(|(i, l)|
+                ("Lock", LOCK_ENTITY_UID, l.tile, vec![get_field("lock", LOCK_FIELD_UID, "Int", json!(i))])
+            ))
+            .chain(self.keys.iter().map(|k|
+                ("Key", KEY_ENTITY_UID, k.tile, vec![get_field("lock", KEY_LOCK_FIELD_UID, "Int", json!(k.lock))])
+            ))
+            .chain(self.placements.iter().filter_map(|p| {
+                let (_, id, uid, field_uid, _) = PLACEMENT_DEFS.iter().find(|d| d.0 == p.kind)?;
+                Some((*id, *uid, p.tile, vec![get_field("name", *field_uid, "String", json!(p.name))]))
+            }))
+            .filter(|(_, _, v, _)| contains(*v));
+        for (identifier, def_uid, v, fields) in points {
+            entities.push(ctx.get_entity_instance(identifier, def_uid, v, Vector2Int::new(gs, gs), fields));
+        }
+
+        let entities_layer = ctx.get_layer_instance("Entities", "Entities", ENTITIES_LAYER_UID, Vec::new(), entities);
+        let tiles_layer = ctx.get_layer_instance("Tiles", "IntGrid", TILES_LAYER_UID, csv, Vec::new());
+
+        json!({
+            "identifier": ctx.identifier,
+            "iid": ctx.iids.next(),
+            "uid": ctx.uid,
+            "worldX": world.x,
+            "worldY": world.y,
+            "worldDepth": 0,
+            "pxWid": c_wid * gs,
+            "pxHei": c_hei * gs,
+            "__bgColor": "#696A79",
+            "bgColor": null,
+            "useAutoIdentifier": false,
+            "bgRelPath": null,
+            "bgPos": null,
+            "bgPivotX": 0.5,
+            "bgPivotY": 0.5,
+            "__smartColor": "#ADADB5",
+            "__bgPos": null,
+            "externalRelPath": null,
+            "fieldInstances": [],
+            "layerInstances": [entities_layer, tiles_layer],
+            "__neighbours": []
+        })
+    }
+}
+
+// deterministic uuid-formatted ids, so repeated exports diff cleanly
+#[derive(Default)]
+struct IidGenerator {
+    next: Cell<u64>
+}
+impl IidGenerator {
+    fn next(&self) -> String {
+        let n = self.next.get();
+        self.next.set(n + 1);
+        format!("00000000-0000-4000-8000-{:012x}", n)
+    }
+}
+
+// what the instances of a single level share
+struct LevelContext<'a> {
+    identifier: String,
+    uid: u32,
+    // first and last tile of the level
+    bounds: (Vector2Int, Vector2Int),
+    // only the rooms of this area, all of them when None
+    area: Option<usize>,
+    grid_size: i32,
+    iids: &'a IidGenerator
+}
+impl LevelContext<'_> {
+    fn get_size(&self) -> (i32, i32) {
+        (self.bounds.1.x - self.bounds.0.x + 1, self.bounds.1.y - self.bounds.0.y + 1)
+    }
+    fn get_world(&self) -> Vector2Int {
+        // world position of the level's top left corner in pixels
+        self.bounds.0 * self.grid_size
+    }
+    fn get_entity_instance(
+        &self,
+        identifier: &str,
+        def_uid: u32,
+        tile: Vector2Int,
+        size: Vector2Int,
+        fields: Vec<Value>
+    ) -> Value {
+        // grid position relative to the level
+        let cell = tile - self.bounds.0;
+        let px = cell * self.grid_size;
+        let world = self.get_world();
+        json!({
+            "__identifier": identifier,
+            "__grid": [cell.x, cell.y],
+            "__pivot": [0, 0],
+            "__tags": [],
+            "__tile": null,
+            "__smartColor": null,
+            "__worldX": world.x + px.x,
+            "__worldY": world.y + px.y,
+            "iid": self.iids.next(),
+            "width": size.x,
+            "height": size.y,
+            "defUid": def_uid,
+            "px": [px.x, px.y],
+            "fieldInstances": fields
+        })
+    }
+    fn get_layer_instance(
+        &self,
+        identifier: &str,
+        layer_type: &str,
+        def_uid: u32,
+        int_grid: Vec<u8>,
+        entities: Vec<Value>
+    ) -> Value {
+        let size = self.get_size();
+        json!({
+            "__identifier": identifier,
+            "__type": layer_type,
+            "__cWid": size.0,
+            "__cHei": size.1,
+            "__gridSize": self.grid_size,
+            "__opacity": 1,
+            "__pxTotalOffsetX": 0,
+            "__pxTotalOffsetY": 0,
+            "__tilesetDefUid": null,
+            "__tilesetRelPath": null,
+            "iid": self.iids.next(),
+            "levelId": self.uid,
+            "layerDefUid": def_uid,
+            "pxOffsetX": 0,
+            "pxOffsetY": 0,
+            "visible": true,
+            "optionalRules": [],
+            "intGridCsv": int_grid,
+            "autoLayerTiles": [],
+            "seed": 0,
+            "overrideTilesetUid": null,
+            "gridTiles": [],
+            "entityInstances": entities
+        })
+    }
+}
+
+fn get_layer_def(identifier: &str, layer_type: &str, uid: u32, grid_size: u32, int_grid_values: Vec<Value>) -> Value {
+    json!({
+        "__type": layer_type,
+        "identifier": identifier,
+        "type": layer_type,
+        "uid": uid,
+        "doc": null,
+        "uiColor": null,
+        "gridSize": grid_size,
+        "guideGridWid": 0,
+        "guideGridHei": 0,
+        "displayOpacity": 1,
+        "inactiveOpacity": 1,
+        "hideInList": false,
+        "hideFieldsWhenInactive": true,
+        "canSelectWhenInactive": true,
+        "renderInWorldView": true,
+        "pxOffsetX": 0,
+        "pxOffsetY": 0,
+        "parallaxFactorX": 0,
+        "parallaxFactorY": 0,
+        "parallaxScaling": true,
+        "requiredTags": [],
+        "excludedTags": [],
+        "autoTilesKilledByOtherLayerUid": null,
+        "uiFilterTags": [],
+        "useAsyncRender": false,
+        "intGridValues": int_grid_values,
+        "intGridValuesGroups": [],
+        "autoRuleGroups": [],
+        "autoSourceLayerDefUid": null,
+        "tilesetDefUid": null,
+        "tilePivotX": 0,
+        "tilePivotY": 0,
+        "biomeFieldUid": null
+    })
+}
+
+fn get_entity_def(identifier: &str, uid: u32, grid_size: u32, color: &str, is_room: bool, fields: &[(&str, u32, &str)]) -> Value {
+    json!({
+        "identifier": identifier,
+        "uid": uid,
+        "tags": [],
+        "exportToToc": false,
+        "allowOutOfBounds": false,
+        "doc": null,
+        "width": grid_size,
+        "height": grid_size,
+        "resizableX": is_room,
+        "resizableY": is_room,
+        "minWidth": null,
+        "maxWidth": null,
+        "minHeight": null,
+        "maxHeight": null,
+        "keepAspectRatio": false,
+        "tileOpacity": 1,
+        "fillOpacity": 0.08,
+        "lineOpacity": 1,
+        "hollow": is_room,
+        "color": color,
+        "renderMode": "Rectangle",
+        "showName": true,
+        "tilesetId": null,
+        "tileRenderMode": "FitInside",
+        "tileRect": null,
+        "uiTileRect": null,
+        "nineSliceBorders": [],
+        "maxCount": 0,
+        "limitScope": "PerLevel",
+        "limitBehavior": "MoveLastOne",
+        "pivotX": 0,
+        "pivotY": 0,
+        "fieldDefs": fields.iter().map(|(id, uid, t)| get_field_def(id, *uid, t)).collect::<Vec<_>>()
+    })
+}
+
+fn get_field_def(identifier: &str, uid: u32, field_type: &str) -> Value {
+    json!({
+        "identifier": identifier,
+        "doc": null,
+        "__type": field_type,
+        "uid": uid,
+        "type": format!("F_{}", field_type),
+        "isArray": false,
+        "canBeNull": false,
+        "arrayMinLength": null,
+        "arrayMaxLength": null,
+        "editorDisplayMode": "Hidden",
+        "editorDisplayScale": 1,
+        "editorDisplayPos": "Above",
+        "editorLinkStyle": "StraightArrow",
+        "editorDisplayColor": null,
+        "editorAlwaysShow": false,
+        "editorShowInWorld": true,
+        "editorCutLongValues": true,
+        "editorTextSuffix": null,
+        "editorTextPrefix": null,
+        "useForSmartColor": false,
+        "exportToToc": false,
+        "searchable": false,
+        "min": null,
+        "max": null,
+        "regex": null,
+        "acceptFileTypes": null,
+        "defaultOverride": null,
+        "textLanguageMode": null,
+        "symmetricalRef": false,
+        "autoChainRef": true,
+        "allowOutOfLevelRef": true,
+        "allowedRefs": "OnlySame",
+        "allowedRefsEntityUid": null,
+        "allowedRefTags": [],
+        "tilesetUid": null
+    })
+}
+
+fn get_field(identifier: &str, def_uid: u32, field_type: &str, value: Value) -> Value {
+    json!({
+        "__identifier": identifier,
+        "__type": field_type,
+        "__value": value,
+        "__tile": null,
+        "defUid": def_uid,
+        "realEditorValues": [{ "id": format!("V_{}", field_type), "params": [value] }]
+    })
+}
@@ -0,0 +1,112 @@
+mod animation;
+mod ascii;
+mod ldtk;
+mod render;
+mod svg;
+mod tiled;
+
+pub use animation::AnimationSettings;
+pub use ascii::AsciiSettings;
+pub use ldtk::LdtkSettings;
+pub use render::{Colour, RenderSettings, AREA_COLOURS, TRANSPARENT};
+pub use svg::SvgSettings;
+pub use tiled::TiledSettings;
+
+pub(super) fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dungeon::{Area, ConnectionStrategy, Dungeon, RoomGenerator, Tunneler};
+    use super::*;
+
+    fn get_empty() -> Vec<Dungeon> {
+        // a bare dungeon and one with an area that wasn't generated yet
+        let mut with_area = Dungeon::new();
+        with_area.add_area(Area::new(
+            RoomGenerator::Grow { count: 3, min_size: 3, max_size: 5 },
+            Tunneler::LShape,
+            ConnectionStrategy::Basic
+        ));
+        vec![Dungeon::new(), with_area]
+    }
+
+    #[test]
+    fn empty_dungeon_exports() {
+        for d in get_empty() {
+            assert_eq!(d.get_bounds(), None);
+            assert_eq!(d.get_tile_map().width, 0);
+            assert_eq!(d.get_distance_map(&[], None).width, 0);
+            assert!(d.to_ascii(&AsciiSettings::default()).trim().is_empty());
+            assert!(d.to_svg(&SvgSettings::default()).contains("<svg"));
+            assert!(d.to_tmx(&TiledSettings::default()).contains("<map"));
+            d.to_ldtk(&LdtkSettings::default());
+            d.to_ldtk(&LdtkSettings { level_per_area: true, ..Default::default() });
+            assert_eq!(d.render_img(&RenderSettings::default()).width(), 0);
+            let frames = d.render_frames(&[], &AnimationSettings::default());
+            assert!(frames.iter().all(|(f, _)| f.width() == 0));
+            // the encoder may refuse an empty image, but mustn't panic
+            let _ = d.write_img(Vec::new(), &RenderSettings::default());
+            let path = std::env::temp_dir().join("dun_gen_empty.gif");
+            let _ = d.save_gif(path.to_str().unwrap(), &[], &AnimationSettings::default());
+        }
+    }
+
+    #[test]
+    fn ldtk_matches_tmx_objects() {
+        use crate::dungeon::{CycleTemplate, EntityKind, Placement};
+
+        let mut d = Dungeon::new();
+        d.seed = 32;
+        for strategy in [ConnectionStrategy::Cycle(CycleTemplate::LockKey), ConnectionStrategy::Basic] {
+            d.add_area(Area::new(
+                RoomGenerator::GrowSeparated { count: 6, min_size: 3, max_size: 5 },
+                Tunneler::LShape,
+                strategy
+            ));
+        }
+        d.generate();
+        d.place_stairs(1.);
+        let tile = d.get_room((1, 0)).centre();
+        d.placements.push(Placement { name: "\"quoted\" name".to_string(), kind: EntityKind::Loot, tile, room: (1, 0) });
+
+        let tmx = d.to_tmx(&TiledSettings::default());
+        let ldtk: serde_json::Value = serde_json::from_str(&d.to_ldtk(&LdtkSettings::default())).unwrap();
+        let instances = ldtk["levels"][0]["layerInstances"][0]["entityInstances"].as_array().unwrap();
+        let count = |id: &str| instances.iter().filter(|e| e["__identifier"] == id).count();
+        for (tmx_type, ldtk_id) in [("room", "Room"), ("door", "Door"), ("lock", "Lock"), ("key", "Key"), ("loot", "Loot")] {
+            assert_eq!(tmx.matches(&format!("type=\"{}\"", tmx_type)).count(), count(ldtk_id), "{}", ldtk_id);
+        }
+        assert!(count("Lock") > 0);
+        let loot = instances.iter().find(|e| e["__identifier"] == "Loot").unwrap();
+        assert_eq!(loot["fieldInstances"][0]["__value"], "\"quoted\" name");
+    }
+
+    #[test]
+    fn ldtk_uids_are_unique() {
+        let mut d = Dungeon::new();
+        d.seed = 32;
+        for _ in 0..3 {
+            d.add_area(Area::new(
+                RoomGenerator::Grow { count: 3, min_size: 3, max_size: 5 },
+                Tunneler::LShape,
+                ConnectionStrategy::Basic
+            ));
+        }
+        d.generate();
+        // an area without rooms gets no level, leaving a gap in the uids
+        d.areas[0].rooms.clear();
+        let ldtk: serde_json::Value = serde_json::from_str(
+            &d.to_ldtk(&LdtkSettings { level_per_area: true, ..Default::default() })
+        ).unwrap();
+        let uids = ldtk["levels"].as_array().unwrap().iter()
+            .map(|l| l["uid"].as_u64().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(uids.len(), 2);
+        assert!(uids.iter().all(|u| *u < ldtk["nextUid"].as_u64().unwrap()));
+    }
+}
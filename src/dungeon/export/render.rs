@@ -0,0 +1,163 @@
+use image::{codecs::png::PngEncoder, ImageEncoder, ImageResult, Rgba, RgbaImage};
+use std::{collections::HashMap, io::Write};
+
+use crate::vectors::Vector2Int;
+
+use super::super::{DistanceMap, Dungeon, TileKind};
+
+pub type Colour = [u8; 4];
+
+pub const TRANSPARENT: Colour = [0, 0, 0, 0];
+// a set of distinct colours that can be used for the areas
+pub const AREA_COLOURS: [Colour; 8] = [
+    [214, 96, 77, 255], [77, 146, 214, 255], [120, 196, 92, 255], [204, 170, 60, 255],
+    [160, 100, 210, 255], [70, 190, 180, 255], [220, 130, 180, 255], [150, 150, 150, 255]
+];
+
+pub struct RenderSettings {
+    // size of a single tile in pixels
+    pub scale: u32,
+    // number of empty tiles around the dungeon bounds, so the outer walls are visible
+    pub margin: u32,
+    pub background: Colour,
+    // tile kinds missing from the palette are drawn as background
+    pub palette: HashMap<TileKind, Colour>,
+    // room tiles get their area's colour (cycled), disabled when empty
+    pub area_colours: Vec<Colour>,
+    pub grid_lines: Option<Colour>,
+    // when false doors are drawn as corridors
+    pub doors: bool,
+    // entrance and exit marker colours
+    pub stairs: Option<(Colour, Colour)>,
+    // distance map drawn as a heat gradient over the floor
+    pub heat: Option<DistanceMap>
+}
+impl Default for RenderSettings {
+    fn default() -> Self {
+        let floor = [150, 150, 50, 255];
+        RenderSettings {
+            scale: 8,
+            margin: 0,
+            background: [0, 0, 0, 255],
+            palette: HashMap::from([
+                (TileKind::Room, floor),
+                (TileKind::Corridor, floor),
+                (TileKind::Door, floor)
+            ]),
+            area_colours: Vec::new(),
+            grid_lines: None,
+            doors: false,
+            stairs: None,
+            heat: None
+        }
+    }
+}
+
+impl Dungeon {
+    pub fn render_img(&self, settings: &RenderSettings) -> RgbaImage {
+        // every tile is exactly scale x scale pixels
+        let (a, b) = self.get_padded_bounds(settings.margin as i32);
+        let map = self.get_tile_map();
+        let areas = self.get_area_map();
+        let max_heat = settings.heat.as_ref()
+            .and_then(|h| h.values.iter().flatten().max().copied())
+            .unwrap_or(0);
+
+        let scale = settings.scale.max(1);
+        let w = (b.x - a.x + 1) as u32;
+        let h = (b.y - a.y + 1) as u32;
+        let mut buf = RgbaImage::from_pixel(w * scale, h * scale, Rgba(settings.background));
+
+        for ty in 0..h {
+            for tx in 0..w {
+                let v = a + Vector2Int::new(tx as i32, ty as i32);
+                let mut kind = *map.get(v).unwrap_or(&TileKind::Empty);
+                if kind == TileKind::Door && !settings.doors { kind = TileKind::Corridor }
+
+                let mut colour = match (kind, areas.get(v).copied().flatten()) {
+                    (TileKind::Room, Some(idx)) if !settings.area_colours.is_empty() => {
+                        settings.area_colours[idx % settings.area_colours.len()]
+                    },
+                    _ => *settings.palette.get(&kind).unwrap_or(&settings.background)
+                };
+                if let Some(d) = settings.heat.as_ref().and_then(|h| h.get(v).copied().flatten()) {
+                    colour = blend(colour, get_heat_colour(d, max_heat), 0.6);
+                }
+                if let Some((entrance, exit)) = settings.stairs {
+                    if Some(v) == self.entrance { colour = entrance }
+                    if Some(v) == self.exit { colour = exit }
+                }
+                fill_tile(&mut buf, tx, ty, scale, colour);
+            }
+        }
+
+        if let Some(colour) = settings.grid_lines {
+            // the closing lines run along the last pixel column and row
+            let (last_x, last_y) = (w * scale - 1, h * scale - 1);
+            for (x, y, pixel) in buf.enumerate_pixels_mut() {
+                if x % scale == 0 || y % scale == 0 || x == last_x || y == last_y { *pixel = Rgba(colour) }
+            }
+        }
+        buf
+    }
+    pub fn save_img(&self, path: &str, scale: u32) -> ImageResult<()> {
+        self.save_img_with(path, &RenderSettings { scale, ..Default::default() })
+    }
+    pub fn save_img_with(&self, path: &str, settings: &RenderSettings) -> ImageResult<()> {
+        self.render_img(settings).save(path)
+    }
+    pub fn write_img(&self, w: impl Write, settings: &RenderSettings) -> ImageResult<()> {
+        // png encoded, eg. to a socket or an in-memory buffer
+        let img = self.render_img(settings);
+        PngEncoder::new(w).write_image(img.as_raw(), img.width(), img.height(), image::ColorType::Rgba8)
+    }
+}
+
+pub(super) fn fill_tile(buf: &mut RgbaImage, tx: u32, ty: u32, scale: u32, colour: Colour) {
+    for y in ty * scale..(ty + 1) * scale {
+        for x in tx * scale..(tx + 1) * scale {
+            buf.put_pixel(x, y, Rgba(colour));
+        }
+    }
+}
+
+fn get_heat_colour(d: u32, max: u32) -> Colour {
+    // blue when close to the goals, red when far away
+    let t = match max {
+        0 => 0.,
+        _ => d as f32 / max as f32
+    };
+    [(255. * t) as u8, 0, (255. * (1. - t)) as u8, 255]
+}
+
+fn blend(a: Colour, b: Colour, t: f32) -> Colour {
+    let mut c = [0; 4];
+    for i in 0..4 {
+        c[i] = (a[i] as f32 * (1. - t) + b[i] as f32 * t) as u8;
+    }
+    // keep heat visible on a transparent background
+    c[3] = a[3].max(b[3]);
+    c
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_lines_are_closed() {
+        let mut d = Dungeon::new();
+        d.tiles.extend([Vector2Int::new(0, 0), Vector2Int::new(1, 0), Vector2Int::new(1, 1)]);
+        let line = [255, 0, 0, 255];
+        let settings = RenderSettings { scale: 4, margin: 1, grid_lines: Some(line), ..Default::default() };
+        let img = d.render_img(&settings);
+        let (w, h) = (img.width(), img.height());
+        assert_eq!((w, h), (16, 16));
+        for (x, y) in [(0, 0), (w - 1, h - 1), (w - 1, 0), (0, h - 1), (w - 1, 5), (5, h - 1)] {
+            assert_eq!(img.get_pixel(x, y).0, line, "{} {}", x, y);
+        }
+        // tile insides stay clear
+        assert_ne!(img.get_pixel(w - 2, h - 2).0, line);
+        assert_ne!(img.get_pixel(1, 1).0, line);
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt::Write;
+
+use crate::vectors::Vector2Int;
+
+use super::super::{Dungeon, EdgeKind};
+
+pub struct SvgSettings {
+    // size of a single tile in svg units
+    pub scale: u32,
+    // draw every floor tile underneath, eg. tunnels dug from a start outside of the rooms
+    pub floor: bool
+}
+impl Default for SvgSettings {
+    fn default() -> Self {
+        SvgSettings { scale: 16, floor: true }
+    }
+}
+
+impl Dungeon {
+    pub fn to_svg(&self, settings: &SvgSettings) -> String {
+        let s = settings.scale as i32;
+        let (a, b) = self.get_padded_bounds(0);
+        // leave a one tile margin for the area outlines
+        let origin = a - Vector2Int::new(1, 1);
+        let size = (b - a + Vector2Int::new(3, 3)) * s;
+        let to_px = |v: Vector2Int| (v - origin) * s;
+        // centre of the tile, used for lines and markers
+        let to_centre = |v: Vector2Int| to_px(v) + Vector2Int::new(s / 2, s / 2);
+
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif">"#,
+            w = size.x, h = size.y
+        );
+        let _ = writeln!(out, r##"<rect width="100%" height="100%" fill="#ffffff"/>"##);
+
+        if settings.floor {
+            let _ = writeln!(out, r##"<g id="floor" fill="#e4e4dc">"##);
+            for (y, x0, x1) in self.get_floor_runs() {
+                let v = to_px(Vector2Int::new(x0, y));
+                let _ = writeln!(out, r#"<rect x="{}" y="{}" width="{}" height="{}"/>"#, v.x, v.y, (x1 - x0 + 1) * s, s);
+            }
+            let _ = writeln!(out, "</g>");
+        }
+
+        let _ = writeln!(
+            out,
+            r##"<g id="corridors" fill="none" stroke="#8c8c6e" stroke-width="{}" stroke-linecap="square" stroke-linejoin="round">"##,
+            s / 2
+        );
+        let paths = self.areas.iter()
+            .flat_map(|a| a.paths.iter().enumerate().map(move |(i, p)| {
+                // mission edges match the area paths by index
+                let kind = a.mission.as_ref().and_then(|m| m.edges.get(i)).map(|e| e.kind);
+                (p, kind.unwrap_or(EdgeKind::Normal))
+            }))
+            .chain(self.corridors.iter().map(|c| (&c.path, EdgeKind::Normal)))
+            .chain(self.start_tunnels.iter().map(|(_, p)| (p, EdgeKind::Normal)));
+        for (path, kind) in paths {
+            let style = match kind {
+                EdgeKind::Normal => "",
+                EdgeKind::Locked => r##" stroke="#c03030""##,
+                EdgeKind::Hidden => r#" stroke-dasharray="2 4""#
+            };
+            for run in split_runs(path) {
+                let points = run.iter()
+                    .map(|v| { let p = to_centre(*v); format!("{},{}", p.x, p.y) })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let _ = writeln!(out, r#"<polyline points="{}"{}/>"#, points, style);
+            }
+        }
+        let _ = writeln!(out, "</g>");
+
+        let _ = writeln!(out, r##"<g id="rooms" fill="#c8c8a0" stroke="#505040" stroke-width="1">"##);
+        for ((area_idx, room_idx), room) in self.get_rooms() {
+            let v = to_px(room.a);
+            let d = (room.b - room.a + Vector2Int::new(1, 1)) * s;
+            let c = to_px(room.a) + d / 2;
+            let _ = writeln!(
+                out,
+                r##"<rect x="{}" y="{}" width="{}" height="{}"/><text x="{}" y="{}" font-size="{}" text-anchor="middle" dominant-baseline="central" fill="#303020" stroke="none">{}.{}</text>"##,
+                v.x, v.y, d.x, d.y, c.x, c.y, s, area_idx, room_idx
+            );
+        }
+        let _ = writeln!(out, "</g>");
+
+        let _ = writeln!(
+            out,
+            r##"<g id="areas" fill="none" stroke="#4060a0" stroke-width="1" stroke-dasharray="{} {}">"##,
+            s / 2, s / 4
+        );
+        for (i, area) in self.areas.iter().enumerate() {
+            if area.rooms.is_empty() { continue }
+            let (aa, ab) = area.get_bounds();
+            let v = to_px(aa) - Vector2Int::new(s / 2, s / 2);
+            let d = (ab - aa + Vector2Int::new(2, 2)) * s;
+            let _ = writeln!(
+                out,
+                r##"<rect x="{}" y="{}" width="{}" height="{}"/><text x="{}" y="{}" font-size="{}" fill="#4060a0" stroke="none">Area {}</text>"##,
+                v.x, v.y, d.x, d.y, v.x + 2, v.y - 2, s * 3 / 4, i
+            );
+        }
+        let _ = writeln!(out, "</g>");
+
+        for (v, colour) in [(self.entrance, "#30b040"), (self.exit, "#c03030")] {
+            if let Some(v) = v {
+                let p = to_centre(v);
+                let _ = writeln!(out, r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#, p.x, p.y, s / 3, colour);
+            }
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+    pub fn save_svg(&self, path: &str, settings: &SvgSettings) -> std::io::Result<()> {
+        std::fs::write(path, self.to_svg(settings))
+    }
+    fn get_floor_runs(&self) -> Vec<(i32, i32, i32)> {
+        // horizontal runs of floor tiles as (y, start x, end x),
+        // to keep the output small
+        let mut tiles = self.tiles.iter().copied().collect::<Vec<_>>();
+        tiles.sort_by_key(|v| (v.y, v.x));
+        let mut runs: Vec<(i32, i32, i32)> = Vec::new();
+        for v in tiles {
+            match runs.last_mut() {
+                Some(run) if run.0 == v.y && run.2 + 1 == v.x => run.2 = v.x,
+                _ => runs.push((v.y, v.x, v.x))
+            }
+        }
+        runs
+    }
+}
+
+fn split_runs(path: &[Vector2Int]) -> Vec<Vec<Vector2Int>> {
+    // tunnel paths are not always ordered (eg. the l-shape),
+    // so split them wherever two consecutive tiles are not adjacent
+    let mut runs: Vec<Vec<Vector2Int>> = Vec::new();
+    for v in path {
+        match runs.last_mut() {
+            Some(run) if run.last().unwrap().manhattan(*v) == 1 => run.push(*v),
+            _ => runs.push(vec![*v])
+        }
+    }
+    runs
+}
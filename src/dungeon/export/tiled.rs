@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::vectors::Vector2Int;
+
+use super::super::{Dungeon, TileKind};
+use super::escape_xml;
+
+pub struct TiledSettings {
+    pub tile_size: u32,
+    // path to an external .tsx tileset
+    pub tileset: String,
+    pub first_gid: u32,
+    // local tile ids within the tileset, missing kinds are left empty
+    pub tiles: HashMap<TileKind, u32>
+}
+impl Default for TiledSettings {
+    fn default() -> Self {
+        TiledSettings {
+            tile_size: 16,
+            tileset: "dungeon.tsx".to_string(),
+            first_gid: 1,
+            tiles: HashMap::from([
+                (TileKind::Wall, 0),
+                (TileKind::Room, 1),
+                (TileKind::Corridor, 2),
+                (TileKind::Door, 3)
+            ])
+        }
+    }
+}
+
+impl Dungeon {
+    pub fn to_tmx(&self, settings: &TiledSettings) -> String {
+        let map = self.get_tile_map();
+        let ts = settings.tile_size as i32;
+        // pixel position of the tile's top left corner
+        let to_px = |v: Vector2Int| (v - map.origin) * ts;
+
+        let csv = (0..map.height)
+            .map(|y| map.values[y * map.width..(y + 1) * map.width].iter()
+                .map(|k| match settings.tiles.get(k) {
+                    Some(id) => (settings.first_gid + id).to_string(),
+                    None => "0".to_string()
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+            )
+            .collect::<Vec<_>>()
+            .join(",\n");
+
+        let mut objects = String::new();
+        let mut object_id = 1;
+        let difficulties = self.get_difficulties();
+        for ((area_idx, room_idx), room) in self.get_rooms() {
+            let v = to_px(room.a);
+            let size = (room.b - room.a + Vector2Int::new(1, 1)) * ts;
+            // custom properties are sorted, so repeated exports diff cleanly
+            let mut properties = room.properties.iter().collect::<Vec<_>>();
+            properties.sort();
+            let custom = properties.iter()
+                .map(|(k, v)| format!("    <property name=\"{}\" value=\"{}\"/>\n", escape_xml(k), escape_xml(v)))
+                .collect::<String>();
+            let _ = writeln!(
+                objects,
+                "  <object id=\"{}\" name=\"room_{}_{}\" type=\"room\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\">\n   <properties>\n    <property name=\"area\" type=\"int\" value=\"{}\"/>\n    <property name=\"room\" type=\"int\" value=\"{}\"/>\n    <property name=\"kind\" value=\"{}\"/>\n    <property name=\"difficulty\" type=\"float\" value=\"{:.3}\"/>\n{}   </properties>\n  </object>",
+                object_id, area_idx, room_idx, v.x, v.y, size.x, size.y, area_idx, room_idx,
+                escape_xml(room.kind.get_name()), difficulties[&(area_idx, room_idx)], custom
+            );
+            object_id += 1;
+        }
+        let points = self.get_doors().into_iter().map(|v| ("door", v))
+            .chain(self.entrance.map(|v| ("start", v)))
+            .chain(self.exit.map(|v| ("exit", v)));
+        for (name, v) in points {
+            // points are placed in the tile centre
+            let v = to_px(v) + Vector2Int::new(ts / 2, ts / 2);
+            let _ = writeln!(
+                objects,
+                "  <object id=\"{}\" name=\"{}\" type=\"{}\" x=\"{}\" y=\"{}\">\n   <point/>\n  </object>",
+                object_id, name, name, v.x, v.y
+            );
+            object_id += 1;
+        }
+        // locks and keys reference each other by the lock index
+        let locks = self.locks.iter().enumerate().map(|(i, l)| ("lock", i, l.tile))
+            .chain(self.keys.iter().map(|k| ("key", k.lock, k.tile)));
+        for (name, lock, v) in locks {
+            let v = to_px(v) + Vector2Int::new(ts / 2, ts / 2);
+            let _ = writeln!(
+                objects,
+                "  <object id=\"{}\" name=\"{}_{}\" type=\"{}\" x=\"{}\" y=\"{}\">\n   <properties>\n    <property name=\"lock\" type=\"int\" value=\"{}\"/>\n   </properties>\n   <point/>\n  </object>",
+                object_id, name, lock, name, v.x, v.y, lock
+            );
+            object_id += 1;
+        }
+
+        for p in self.placements.iter() {
+            let v = to_px(p.tile) + Vector2Int::new(ts / 2, ts / 2);
+            let kind = format!("{:?}", p.kind).to_lowercase();
+            let _ = writeln!(
+                objects,
+                "  <object id=\"{}\" name=\"{}\" type=\"{}\" x=\"{}\" y=\"{}\">\n   <point/>\n  </object>",
+                object_id, escape_xml(&p.name), kind, v.x, v.y
+            );
+            object_id += 1;
+        }
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<map version=\"1.10\" orientation=\"orthogonal\" renderorder=\"right-down\" width=\"{w}\" height=\"{h}\" tilewidth=\"{ts}\" tileheight=\"{ts}\" infinite=\"0\" nextlayerid=\"3\" nextobjectid=\"{next}\">
+ <tileset firstgid=\"{gid}\" source=\"{source}\"/>
+ <layer id=\"1\" name=\"Tiles\" width=\"{w}\" height=\"{h}\">
+  <data encoding=\"csv\">
+{csv}
+</data>
+ </layer>
+ <objectgroup id=\"2\" name=\"Objects\">
+{objects} </objectgroup>
+</map>
+",
+            w = map.width,
+            h = map.height,
+            ts = ts,
+            next = object_id,
+            gid = settings.first_gid,
+            source = escape_xml(&settings.tileset),
+            csv = csv,
+            objects = objects
+        )
+    }
+    pub fn save_tmx(&self, path: &str, settings: &TiledSettings) -> std::io::Result<()> {
+        std::fs::write(path, self.to_tmx(settings))
+    }
+}
@@ -0,0 +1,367 @@
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::cmp::Reverse;
+
+use crate::vectors::Vector2Int;
+
+use super::{Dungeon, EdgeKind, RoomKind, RoomRef};
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Corridor {
+    pub a: RoomRef,
+    pub b: RoomRef,
+    pub path: Vec<Vector2Int>,
+    // secret passage of a mission graph, see CycleTemplate::HiddenShortcut
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hidden: bool
+}
+impl Corridor {
+    pub fn new(a: RoomRef, b: RoomRef, path: Vec<Vector2Int>) -> Self {
+        Corridor { a, b, path, hidden: false }
+    }
+    pub fn get_length(&self) -> usize {
+        self.path.len()
+    }
+    pub fn get_other(&self, room: RoomRef) -> RoomRef {
+        // the opposite end of the corridor
+        if room == self.a { self.b } else { self.a }
+    }
+}
+
+// rooms as nodes, corridors as edges
+// there can be multiple corridors between the same pair of rooms
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+// the adjacency is rebuilt on load (json can't have tuple keys)
+#[cfg_attr(feature = "serde", serde(from = "GraphData", into = "GraphData"))]
+pub struct RoomGraph {
+    pub nodes: Vec<RoomRef>,
+    pub edges: Vec<Corridor>,
+    // edge indexes per node
+    adjacency: HashMap<RoomRef, Vec<usize>>
+}
+impl RoomGraph {
+    pub fn new(nodes: Vec<RoomRef>, edges: Vec<Corridor>) -> Self {
+        let mut adjacency: HashMap<RoomRef, Vec<usize>> = nodes.iter().map(|n| (*n, Vec::new())).collect();
+        for (i, edge) in edges.iter().enumerate() {
+            adjacency.entry(edge.a).or_default().push(i);
+            if edge.b != edge.a { adjacency.entry(edge.b).or_default().push(i) }
+        }
+        RoomGraph { nodes, edges, adjacency }
+    }
+    pub fn get_edges(&self, room: RoomRef) -> impl Iterator<Item=(usize, &Corridor)> {
+        self.adjacency.get(&room)
+            .into_iter()
+            .flatten()
+            .map(|i| (*i, &self.edges[*i]))
+    }
+    pub fn get_neighbours(&self, room: RoomRef) -> Vec<RoomRef> {
+        let mut neighbours = self.get_edges(room)
+            .map(|(_, e)| e.get_other(room))
+            .collect::<Vec<_>>();
+        neighbours.sort();
+        neighbours.dedup();
+        neighbours
+    }
+    pub fn get_degree(&self, room: RoomRef) -> usize {
+        // number of corridors leaving the room
+        self.get_edges(room).count()
+    }
+    pub fn get_shortest_path(&self, start: RoomRef, end: RoomRef) -> Option<Vec<RoomRef>> {
+        // dijkstra over the rooms, weighted by corridor lengths
+        let mut queue = BinaryHeap::new();
+        let mut costs = HashMap::from([(start, 0)]);
+        let mut came_from = HashMap::new();
+        let mut visited = HashSet::new();
+        queue.push(Reverse((0, start)));
+
+        while let Some(Reverse((cost, cur))) = queue.pop() {
+            if cur == end { break }
+            if !visited.insert(cur) { continue }
+            for (_, edge) in self.get_edges(cur) {
+                let n = edge.get_other(cur);
+                let new_cost = cost + edge.get_length();
+                if new_cost >= *costs.get(&n).unwrap_or(&usize::MAX) { continue }
+                costs.insert(n, new_cost);
+                came_from.insert(n, cur);
+                queue.push(Reverse((new_cost, n)));
+            }
+        }
+        if !costs.contains_key(&end) { return None }
+
+        let mut path = vec![end];
+        let mut cur = end;
+        while let Some(prev) = came_from.get(&cur) {
+            path.push(*prev);
+            cur = *prev;
+        }
+        path.reverse();
+        Some(path)
+    }
+    pub fn get_articulation_points(&self) -> Vec<RoomRef> {
+        // rooms whose removal disconnects the graph
+        let mut points = self.get_dfs_lows().1.into_iter().collect::<Vec<_>>();
+        points.sort();
+        points
+    }
+    pub fn get_bridges(&self) -> Vec<usize> {
+        // indexes of the corridors whose removal disconnects the graph
+        let mut bridges = self.get_dfs_lows().2;
+        bridges.sort();
+        bridges
+    }
+    pub fn get_cycles(&self) -> Vec<Vec<RoomRef>> {
+        // a cycle basis: every corridor left out of a spanning forest closes one loop
+        let mut parent: HashMap<RoomRef, (RoomRef, usize)> = HashMap::new();
+        let mut depth = HashMap::new();
+        let mut tree_edges = HashSet::new();
+        for root in self.nodes.iter() {
+            if depth.contains_key(root) { continue }
+            depth.insert(*root, 0);
+            let mut stack = vec![*root];
+            while let Some(cur) = stack.pop() {
+                for (i, edge) in self.get_edges(cur) {
+                    let n = edge.get_other(cur);
+                    if depth.contains_key(&n) { continue }
+                    depth.insert(n, depth[&cur] + 1);
+                    parent.insert(n, (cur, i));
+                    tree_edges.insert(i);
+                    stack.push(n);
+                }
+            }
+        }
+
+        let mut cycles = Vec::new();
+        for (i, edge) in self.edges.iter().enumerate() {
+            if tree_edges.contains(&i) { continue }
+            // walk both ends up to the common ancestor
+            let (mut a, mut b) = (edge.a, edge.b);
+            let mut left = vec![a];
+            let mut right = vec![b];
+            while a != b {
+                if depth[&a] >= depth[&b] {
+                    a = parent[&a].0;
+                    left.push(a);
+                } else {
+                    b = parent[&b].0;
+                    right.push(b);
+                }
+            }
+            // the common ancestor is already on the left side
+            right.pop();
+            right.reverse();
+            left.extend(right);
+            cycles.push(left);
+        }
+        cycles
+    }
+    fn get_dfs_lows(&self) -> (HashMap<RoomRef, usize>, HashSet<RoomRef>, Vec<usize>) {
+        // iterative tarjan, returns discovery order, articulation points and bridges
+        let mut order: HashMap<RoomRef, usize> = HashMap::new();
+        let mut low: HashMap<RoomRef, usize> = HashMap::new();
+        let mut points = HashSet::new();
+        let mut bridges = Vec::new();
+
+        for root in self.nodes.iter() {
+            if order.contains_key(root) { continue }
+            order.insert(*root, order.len());
+            low.insert(*root, order[root]);
+            let mut root_children = 0;
+            // (room, edge used to enter it, next edge to check)
+            let mut stack: Vec<(RoomRef, Option<usize>, usize)> = vec![(*root, None, 0)];
+
+            while let Some((cur, via, next)) = stack.last().copied() {
+                let edges = &self.adjacency[&cur];
+                if next < edges.len() {
+                    stack.last_mut().unwrap().2 += 1;
+                    let i = edges[next];
+                    // parallel corridors still count, only the one we came by is skipped
+                    if Some(i) == via { continue }
+                    let n = self.edges[i].get_other(cur);
+                    match order.get(&n) {
+                        Some(o) => {
+                            let l = low[&cur].min(*o);
+                            low.insert(cur, l);
+                        },
+                        None => {
+                            order.insert(n, order.len());
+                            low.insert(n, order[&n]);
+                            if cur == *root { root_children += 1 }
+                            stack.push((n, Some(i), 0));
+                        }
+                    }
+                    continue
+                }
+
+                stack.pop();
+                let Some((parent, _, _)) = stack.last().copied() else { continue };
+                let l = low[&parent].min(low[&cur]);
+                low.insert(parent, l);
+                if low[&cur] > order[&parent] { bridges.push(via.unwrap()) }
+                if parent != *root && low[&cur] >= order[&parent] { points.insert(parent); }
+            }
+            if root_children > 1 { points.insert(*root); }
+        }
+        (order, points, bridges)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GraphData {
+    nodes: Vec<RoomRef>,
+    edges: Vec<Corridor>
+}
+#[cfg(feature = "serde")]
+impl From<GraphData> for RoomGraph {
+    fn from(data: GraphData) -> Self {
+        RoomGraph::new(data.nodes, data.edges)
+    }
+}
+#[cfg(feature = "serde")]
+impl From<RoomGraph> for GraphData {
+    fn from(graph: RoomGraph) -> Self {
+        GraphData { nodes: graph.nodes, edges: graph.edges }
+    }
+}
+
+impl Dungeon {
+    pub fn update_graph(&mut self) {
+        // rebuild the room graph from the area connections
+        // and the corridors joining the areas
+        let nodes = self.get_rooms().map(|(r, _)| r).collect();
+        let mut edges = Vec::new();
+        for (i, area) in self.areas.iter().enumerate() {
+            for (j, ((a, b), path)) in area.connections.iter().zip(area.paths.iter()).enumerate() {
+                let mut corridor = Corridor::new((i, *a), (i, *b), path.clone());
+                // mission edges match the area connections by index
+                corridor.hidden = area.mission.as_ref()
+                    .and_then(|m| m.edges.get(j))
+                    .is_some_and(|e| e.kind == EdgeKind::Hidden);
+                edges.push(corridor);
+            }
+        }
+        edges.extend(self.corridors.iter().cloned());
+        self.graph = RoomGraph::new(nodes, edges);
+        self.tag_junctions();
+    }
+    fn tag_junctions(&mut self) {
+        // tag the rooms branching out the most, once all the corridors are known
+        // rooms tagged otherwise are left alone
+        for (i, area) in self.areas.iter_mut().enumerate() {
+            for (j, room) in area.rooms.iter_mut().enumerate() {
+                let junction = self.graph.get_degree((i, j)) >= 3;
+                match room.kind {
+                    RoomKind::Normal if junction => room.kind = RoomKind::Junction,
+                    RoomKind::Junction if !junction => room.kind = RoomKind::Normal,
+                    _ => ()
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{prelude::*, rngs::StdRng};
+    use crate::dungeon::{Area, ConnectionStrategy, RoomGenerator, Tunneler};
+    use super::*;
+
+    fn get_random_graph(rng: &mut StdRng) -> RoomGraph {
+        // sparse, possibly disconnected, with parallel corridors
+        let nodes = (0..rng.gen_range(1..12)).map(|i| (i % 3, i)).collect::<Vec<_>>();
+        let edges = (0..rng.gen_range(0..nodes.len() * 2))
+            .filter_map(|_| {
+                let a = *nodes.choose(rng).unwrap();
+                let b = *nodes.choose(rng).unwrap();
+                (a != b).then(|| Corridor::new(a, b, Vec::new()))
+            })
+            .collect();
+        RoomGraph::new(nodes, edges)
+    }
+
+    fn count_components(graph: &RoomGraph, removed_node: Option<RoomRef>, removed_edge: Option<usize>) -> usize {
+        // flood fill over the graph, skipping the removed node or corridor
+        let mut seen = HashSet::new();
+        let mut count = 0;
+        for root in graph.nodes.iter() {
+            if Some(*root) == removed_node || !seen.insert(*root) { continue }
+            count += 1;
+            let mut stack = vec![*root];
+            while let Some(cur) = stack.pop() {
+                for (i, edge) in graph.get_edges(cur) {
+                    let n = edge.get_other(cur);
+                    if Some(i) == removed_edge || Some(n) == removed_node { continue }
+                    if seen.insert(n) { stack.push(n) }
+                }
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn articulation_points_match_brute_force() {
+        let mut rng = StdRng::seed_from_u64(43);
+        for _ in 0..500 {
+            let graph = get_random_graph(&mut rng);
+            let base = count_components(&graph, None, None);
+            let mut points = graph.nodes.iter()
+                .filter(|n| count_components(&graph, Some(**n), None) > base)
+                .copied()
+                .collect::<Vec<_>>();
+            points.sort();
+            assert_eq!(graph.get_articulation_points(), points, "{:?}", graph.edges);
+
+            let bridges = (0..graph.edges.len())
+                .filter(|i| count_components(&graph, None, Some(*i)) > base)
+                .collect::<Vec<_>>();
+            assert_eq!(graph.get_bridges(), bridges, "{:?}", graph.edges);
+        }
+    }
+
+    #[test]
+    fn cycle_basis_size() {
+        let mut rng = StdRng::seed_from_u64(43);
+        for _ in 0..500 {
+            let graph = get_random_graph(&mut rng);
+            let cycles = graph.get_cycles();
+            let expected = graph.edges.len() + count_components(&graph, None, None) - graph.nodes.len();
+            assert_eq!(cycles.len(), expected, "{:?}", graph.edges);
+            // every loop is a closed walk over the corridors
+            for cycle in cycles {
+                for (i, a) in cycle.iter().enumerate() {
+                    let b = cycle[(i + 1) % cycle.len()];
+                    assert!(graph.get_neighbours(*a).contains(&b), "{:?}", cycle);
+                }
+            }
+        }
+    }
+
+    fn get_dungeon(seed: u64) -> Dungeon {
+        let mut d = Dungeon::new();
+        d.seed = seed;
+        for strategy in [ConnectionStrategy::Basic, ConnectionStrategy::Secondary(3)] {
+            d.add_area(Area::new(
+                RoomGenerator::Grow { count: 8, min_size: 3, max_size: 5 },
+                Tunneler::LShape,
+                strategy
+            ));
+        }
+        d.generate();
+        d
+    }
+
+    #[test]
+    fn junctions_follow_the_final_graph() {
+        let mut junctions = 0;
+        for seed in 0..30 {
+            let d = get_dungeon(seed);
+            for (r, room) in d.get_rooms() {
+                let expected = if d.graph.get_degree(r) >= 3 { RoomKind::Junction } else { RoomKind::Normal };
+                assert_eq!(room.kind, expected, "seed {} room {:?}", seed, r);
+                if room.kind == RoomKind::Junction { junctions += 1 }
+            }
+        }
+        assert!(junctions > 0);
+    }
+}
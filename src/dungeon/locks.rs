@@ -0,0 +1,411 @@
+use rand::{prelude::*, rngs::StdRng};
+use std::collections::{HashSet, VecDeque};
+
+use crate::vectors::{Vector2Int, ORTHO_DIRECTIONS};
+
+use super::{Dungeon, EdgeKind, MissionRole, RoomRef};
+
+pub struct LockSettings {
+    // number of locked doors to place (less can fit)
+    pub locks: usize,
+    // keys needed to open a single lock
+    pub keys_per_lock: usize,
+    // max number of locks on a single route from the entrance
+    // 1 means that no lock is placed behind another
+    pub max_depth: usize,
+    // place the keys behind the previously opened lock when possible,
+    // otherwise all the keys are reachable from the start
+    pub nested: bool
+}
+impl Default for LockSettings {
+    fn default() -> Self {
+        LockSettings { locks: 3, keys_per_lock: 1, max_depth: 3, nested: true }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lock {
+    // door tile blocked until opened
+    pub tile: Vector2Int,
+    // the room graph corridor it's placed on
+    pub corridor: usize,
+    // first room behind the lock
+    pub room: RoomRef,
+    // locks between the entrance and this one, plus one
+    pub depth: usize
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Key {
+    // index of the lock it opens
+    pub lock: usize,
+    pub tile: Vector2Int,
+    pub room: RoomRef
+}
+
+impl Dungeon {
+    pub fn get_mission_locks(&self) -> (Vec<Lock>, Vec<Key>) {
+        // a lock on the door into the goal of every lock-key cycle,
+        // with its key in the room ending the long route
+        // skipped when some other tunnel runs into the goal, see get_skipped_missions
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let doors = self.get_doors().into_iter().collect::<HashSet<_>>();
+        let mut locks = Vec::new();
+        let mut keys = Vec::new();
+        // graph index of the area's first corridor, see update_graph
+        let mut first = 0;
+        for (i, area) in self.areas.iter().enumerate() {
+            let offset = first;
+            first += area.connections.len();
+            let Some(mission) = &area.mission else { continue };
+            let (Some(goal), Some(key)) = (mission.get_locked_room(), mission.get_room(MissionRole::Key)) else { continue };
+            let Some(edge) = mission.edges.iter().position(|e| e.kind == EdgeKind::Locked) else { continue };
+            let corridor = offset + edge;
+            let start = self.get_room((i, mission.rooms[0])).centre();
+            let behind = HashSet::from([(i, goal)]);
+            let Some(tile) = self.get_lock_tile(start, corridor, (i, goal), &behind, &doors) else { continue };
+            // the centre is left for the stairs
+            let Some(key_tile) = self.get_free_tile((i, key), &HashSet::from([self.get_room((i, key)).centre()]), &mut rng) else { continue };
+            keys.push(Key { lock: locks.len(), tile: key_tile, room: (i, key) });
+            locks.push(Lock { tile, corridor, room: (i, goal), depth: 1 });
+        }
+        (locks, keys)
+    }
+    pub fn get_skipped_missions(&self) -> Vec<usize> {
+        // areas whose lock-key cycle got no lock, as the goal can be reached around it
+        self.areas.iter()
+            .enumerate()
+            .filter_map(|(i, a)| Some((i, a.mission.as_ref()?.get_locked_room()?)))
+            .filter(|(i, goal)| !self.locks.iter().any(|l| l.room == (*i, *goal)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+    pub fn get_locks(&self, settings: &LockSettings) -> Option<(Vec<Lock>, Vec<Key>)> {
+        // locks are returned in an order they can be opened in
+        // requires the entrance to be placed inside of a room
+        // the mission locks are left out, but stay closed while the keys are placed
+        let entrance = self.entrance?;
+        let start = self.get_rooms().find(|(_, r)| r.get_tiles().contains(&entrance))?.0;
+        let keys_per_lock = settings.keys_per_lock.max(1);
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let doors = self.get_doors().into_iter().collect::<HashSet<_>>();
+        let (mission_locks, mission_keys) = self.get_mission_locks();
+        let sealed = mission_locks.iter().map(|l| l.corridor).collect::<Vec<_>>();
+
+        // rooms that don't need a mission key
+        let open = self.get_room_region(start, &sealed);
+
+        // only bridges can be locked, otherwise there'd be a way around
+        let mut candidates = Vec::new();
+        for corridor in self.graph.get_bridges() {
+            if sealed.contains(&corridor) { continue }
+            // locks behind a mission lock would have no room left for their keys
+            let edge = &self.graph.edges[corridor];
+            if !open.contains(&edge.a) && !open.contains(&edge.b) { continue }
+            let near = self.get_room_region(start, &[corridor]);
+            let behind = self.graph.nodes.iter()
+                .filter(|r| !near.contains(r))
+                .copied()
+                .collect::<HashSet<_>>();
+            // the room on the far side of the corridor
+            let room = if behind.contains(&edge.b) { edge.b } else { edge.a };
+            if !behind.contains(&room) { continue }
+            if let Some(tile) = self.get_lock_tile(entrance, corridor, room, &behind, &doors) {
+                candidates.push((corridor, room, tile, behind));
+            }
+        }
+        candidates.shuffle(&mut rng);
+
+        // pick the locks, respecting the depth limit
+        let mut picked: Vec<(usize, RoomRef, Vector2Int, HashSet<RoomRef>)> = Vec::new();
+        for candidate in candidates {
+            if picked.len() >= settings.locks { break }
+            // a lock counts itself, as its room is behind it
+            let depth = |c: &(usize, RoomRef, Vector2Int, HashSet<RoomRef>), picked: &[(usize, RoomRef, Vector2Int, HashSet<RoomRef>)]|
+                picked.iter().filter(|p| p.3.contains(&c.1)).count();
+            // check the new lock and all the ones already behind it
+            let mut all = picked.clone();
+            all.push(candidate.clone());
+            if all.iter().any(|c| depth(c, &all) > settings.max_depth) { continue }
+            picked.push(candidate);
+        }
+
+        // outer locks are opened first
+        let mut locks = picked.iter()
+            .map(|c| Lock {
+                tile: c.2,
+                corridor: c.0,
+                room: c.1,
+                depth: picked.iter().filter(|p| p.3.contains(&c.1)).count()
+            })
+            .collect::<Vec<_>>();
+        locks.sort_by_key(|l| l.depth);
+
+        // region reachable with every lock from idx onwards closed
+        let regions = (0..=locks.len())
+            .map(|i| {
+                let closed = locks[i..].iter().map(|l| l.corridor).chain(sealed.iter().copied()).collect::<Vec<_>>();
+                self.get_room_region(start, &closed)
+            })
+            .collect::<Vec<_>>();
+
+        let mut keys = Vec::new();
+        let mut used = HashSet::from([entrance]);
+        used.extend(self.exit);
+        used.extend(mission_keys.iter().map(|k| k.tile));
+        for (i, _) in locks.iter().enumerate() {
+            // the latest unlocked rooms for chains, otherwise the start region
+            let mut rooms = match (settings.nested, i) {
+                (true, i) if i > 0 => regions[i].difference(&regions[i - 1]).copied().collect::<Vec<_>>(),
+                _ => regions[0].iter().copied().collect::<Vec<_>>()
+            };
+            if rooms.is_empty() { return None }
+            // keep the entrance room free, unless it's the only option
+            if rooms.len() > 1 { rooms.retain(|r| *r != start) }
+            rooms.sort();
+            rooms.shuffle(&mut rng);
+
+            for k in 0..keys_per_lock {
+                let room = rooms[k % rooms.len()];
+                let tile = self.get_free_tile(room, &used, &mut rng)?;
+                used.insert(tile);
+                keys.push(Key { lock: i, tile, room });
+            }
+        }
+
+        let (all_locks, all_keys) = merge_locks((mission_locks, mission_keys), (locks.clone(), keys.clone()));
+        if !self.is_solvable(entrance, &all_locks, &all_keys) { return None }
+        Some((locks, keys))
+    }
+    pub fn place_locks(&mut self, settings: &LockSettings) -> Option<usize> {
+        // returns the number of placed locks, not counting the mission ones
+        // they're kept in front, with the depths counted from the entrance
+        let (locks, keys) = self.get_locks(settings)?;
+        let count = locks.len();
+        (self.locks, self.keys) = merge_locks(self.get_mission_locks(), (locks, keys));
+        self.update_lock_depths();
+        Some(count)
+    }
+    pub(super) fn update_lock_depths(&mut self) {
+        // counts the locks cutting each lock's room off the entrance
+        let Some(start) = self.entrance.and_then(|v| self.get_rooms().find(|(_, r)| r.get_tiles().contains(&v))) else { return };
+        let depths = self.locks.iter()
+            .map(|l| self.locks.iter().filter(|o| !self.get_room_region(start.0, &[o.corridor]).contains(&l.room)).count())
+            .collect::<Vec<_>>();
+        self.locks.iter_mut().zip(depths).for_each(|(l, d)| l.depth = d.max(1));
+    }
+    pub(super) fn drop_bypassed_locks(&mut self) {
+        // drops the locks with a way around them, along with their keys
+        // tunnels dug after the locks were placed can lead into their rooms
+        let kept = self.locks.iter().map(|l| self.is_cut_off(l)).collect::<Vec<_>>();
+        let mut new_idxs = Vec::new();
+        let mut count = 0;
+        for k in kept.iter() {
+            new_idxs.push(k.then_some(count));
+            if *k { count += 1 }
+        }
+        self.keys = self.keys.iter()
+            .filter_map(|k| Some(Key { lock: new_idxs[k.lock]?, ..k.clone() }))
+            .collect();
+        self.locks = self.locks.iter().zip(kept).filter(|(_, k)| *k).map(|(l, _)| l.clone()).collect();
+        self.update_lock_depths();
+    }
+    pub(super) fn is_cut_off(&self, lock: &Lock) -> bool {
+        // with the door closed, its room can't get to the other side of it
+        let inside = get_reachable(self.get_room(lock.room).centre(), &self.tiles, &HashSet::from([lock.tile]));
+        ORTHO_DIRECTIONS.iter()
+            .map(|d| lock.tile + *d)
+            .any(|v| self.tiles.contains(&v) && !inside.contains(&v))
+    }
+    pub fn is_solvable(&self, start: Vector2Int, locks: &[Lock], keys: &[Key]) -> bool {
+        // walk from the start, opening every lock once all its keys are collected
+        // solvable when every lock gets opened and the exit can be reached
+        let mut open = vec![false; locks.len()];
+        loop {
+            let blockers = locks.iter().zip(open.iter())
+                .filter(|(_, o)| !**o)
+                .map(|(l, _)| l.tile)
+                .collect::<HashSet<_>>();
+            let reachable = get_reachable(start, &self.tiles, &blockers);
+            let mut progress = false;
+            for (i, lock) in locks.iter().enumerate() {
+                if open[i] { continue }
+                let has_keys = keys.iter().filter(|k| k.lock == i).all(|k| reachable.contains(&k.tile));
+                let at_door = ORTHO_DIRECTIONS.iter().any(|d| reachable.contains(&(lock.tile + *d)));
+                if has_keys && at_door {
+                    open[i] = true;
+                    progress = true;
+                }
+            }
+            if !progress {
+                return open.iter().all(|o| *o) && self.exit.is_none_or(|v| reachable.contains(&v))
+            }
+        }
+    }
+    fn get_room_region(&self, start: RoomRef, closed: &[usize]) -> HashSet<RoomRef> {
+        // rooms reachable in the room graph, without using the closed corridors
+        let mut region = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(cur) = queue.pop_front() {
+            for (i, edge) in self.graph.get_edges(cur) {
+                if closed.contains(&i) { continue }
+                let n = edge.get_other(cur);
+                if region.insert(n) { queue.push_back(n) }
+            }
+        }
+        region
+    }
+    fn get_lock_tile(
+        &self,
+        entrance: Vector2Int,
+        corridor: usize,
+        room: RoomRef,
+        behind: &HashSet<RoomRef>,
+        doors: &HashSet<Vector2Int>
+    ) -> Option<Vector2Int> {
+        // a door on the corridor, next to the room behind the lock
+        // tunnels can overlap, so it has to actually cut off the rooms on the tile map
+        let room_tiles = self.get_room(room).get_tiles();
+        let mut options = self.graph.edges[corridor].path.iter()
+            .filter(|v| doors.contains(v))
+            .filter(|v| ORTHO_DIRECTIONS.iter().any(|d| room_tiles.contains(&(**v + *d))))
+            .copied()
+            .collect::<Vec<_>>();
+        options.sort_by_key(|v| (v.y, v.x));
+        options.dedup();
+
+        let reachable = get_reachable(entrance, &self.tiles, &HashSet::new());
+        options.into_iter().find(|v| {
+            let cut = get_reachable(entrance, &self.tiles, &HashSet::from([*v]));
+            self.get_rooms().all(|(r, room)| {
+                let c = room.centre();
+                match behind.contains(&r) {
+                    true => !cut.contains(&c),
+                    false => cut.contains(&c) == reachable.contains(&c)
+                }
+            })
+        })
+    }
+    fn get_free_tile(&self, room: RoomRef, used: &HashSet<Vector2Int>, rng: &mut StdRng) -> Option<Vector2Int> {
+        let mut tiles = self.get_room(room).get_tiles().into_iter()
+            .filter(|v| !used.contains(v))
+            .collect::<Vec<_>>();
+        tiles.sort_by_key(|v| (v.y, v.x));
+        tiles.choose(rng).copied()
+    }
+}
+
+pub(super) fn merge_locks(first: (Vec<Lock>, Vec<Key>), second: (Vec<Lock>, Vec<Key>)) -> (Vec<Lock>, Vec<Key>) {
+    // appends the second set, shifting its key indexes
+    let (mut locks, mut keys) = first;
+    let offset = locks.len();
+    locks.extend(second.0);
+    keys.extend(second.1.into_iter().map(|k| Key { lock: k.lock + offset, ..k }));
+    (locks, keys)
+}
+
+fn get_reachable(start: Vector2Int, tiles: &HashSet<Vector2Int>, blockers: &HashSet<Vector2Int>) -> HashSet<Vector2Int> {
+    let mut reachable = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(cur) = queue.pop_front() {
+        for dir in ORTHO_DIRECTIONS {
+            let n = cur + dir;
+            if !tiles.contains(&n) || blockers.contains(&n) { continue }
+            if reachable.insert(n) { queue.push_back(n) }
+        }
+    }
+    reachable
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dungeon::{Area, ConnectionStrategy, CycleTemplate, RoomGenerator, Tunneler};
+    use super::*;
+
+    fn get_dungeon(seed: u64) -> Dungeon {
+        let mut d = Dungeon::new();
+        d.seed = seed;
+        for strategy in [ConnectionStrategy::Basic, ConnectionStrategy::Secondary(1), ConnectionStrategy::Basic] {
+            d.add_area(Area::new(
+                RoomGenerator::Grow { count: 6, min_size: 3, max_size: 5 },
+                Tunneler::LShape,
+                strategy
+            ));
+        }
+        d.generate();
+        d.place_stairs(1.);
+        d
+    }
+
+    #[test]
+    fn generated_locks_are_solvable() {
+        let mut placed = 0;
+        for seed in 0..8 {
+            let d = get_dungeon(seed);
+            let entrance = d.entrance.unwrap();
+            for nested in [true, false] {
+                for keys_per_lock in [1, 2] {
+                    for max_depth in [1, 3] {
+                        let settings = LockSettings { locks: 3, keys_per_lock, max_depth, nested };
+                        let Some((locks, keys)) = d.get_locks(&settings) else { continue };
+                        placed += locks.len();
+                        assert!(d.is_solvable(entrance, &locks, &keys));
+                        assert_eq!(keys.len(), locks.len() * keys_per_lock);
+                        for (i, lock) in locks.iter().enumerate() {
+                            assert!(lock.depth <= max_depth);
+                            // the lock actually cuts off its room
+                            let closed = get_reachable(entrance, &d.tiles, &HashSet::from([lock.tile]));
+                            assert!(!closed.contains(&d.get_room(lock.room).centre()));
+                            // and its keys are reachable with this and the later locks closed
+                            let blockers = locks[i..].iter().map(|l| l.tile).collect();
+                            let reachable = get_reachable(entrance, &d.tiles, &blockers);
+                            assert!(keys.iter().filter(|k| k.lock == i).all(|k| reachable.contains(&k.tile)));
+                        }
+                        // a key moved behind its own lock makes it unsolvable
+                        if let Some(lock) = locks.first() {
+                            let mut moved = keys.clone();
+                            moved[0].tile = d.get_room(lock.room).centre();
+                            assert!(!d.is_solvable(entrance, &locks, &moved));
+                        }
+                    }
+                }
+            }
+        }
+        assert!(placed > 0);
+    }
+
+    #[test]
+    fn locks_skip_the_mission_rooms() {
+        // the layout of recipes/cycles.toml, where locks behind the mission lock
+        // used to be picked without any room left for their keys
+        let mut placed = 0;
+        for seed in 0..24 {
+            let mut d = Dungeon::with_rows(2);
+            d.seed = seed;
+            let areas = [
+                (RoomGenerator::GrowSeparated { count: 6, min_size: 3, max_size: 5 }, Tunneler::Weighted, Some(CycleTemplate::AlternatePaths)),
+                (RoomGenerator::GrowSeparated { count: 7, min_size: 3, max_size: 5 }, Tunneler::Weighted, Some(CycleTemplate::LockKey)),
+                (RoomGenerator::Chamber { min_size: 10, max_size: 12 }, Tunneler::Weighted, None),
+                (RoomGenerator::GrowSeparated { count: 6, min_size: 2, max_size: 5 }, Tunneler::LShape, Some(CycleTemplate::HiddenShortcut))
+            ];
+            for (generator, tunneler, template) in areas {
+                let strategy = template.map_or(ConnectionStrategy::Basic, ConnectionStrategy::Cycle);
+                d.add_area(Area::new(generator, tunneler, strategy));
+            }
+            d.generate();
+            d.place_stairs(1.);
+            let missions = d.get_mission_locks().0.len();
+
+            for max_depth in [1, 3] {
+                let settings = LockSettings { locks: 3, keys_per_lock: 1, max_depth, nested: true };
+                let Some(count) = d.place_locks(&settings) else { continue };
+                placed += count;
+                assert_eq!(d.locks.len(), missions + count);
+                assert!(d.is_solvable(d.entrance.unwrap(), &d.locks, &d.keys), "seed {}", seed);
+            }
+        }
+        assert!(placed > 0);
+    }
+}
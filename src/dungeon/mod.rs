@@ -1,31 +1,102 @@
-use image::{ImageBuffer};
+use rand::{prelude::*, rngs::StdRng};
 use std::collections::HashSet;
 
 use crate::vectors::Vector2Int;
 
 mod area;
+mod chunks;
+mod cycles;
+mod difficulty;
+mod distance;
+mod events;
+mod export;
+mod graph;
+mod locks;
+mod placement;
+mod regenerate;
 mod room;
+mod rules;
+mod stack;
+mod stairs;
+mod tile;
 mod tunnels;
+mod validation;
 
 pub use area::{Area, ConnectionStrategy};
-pub use room::{Room, RoomGenerator};
+pub use chunks::{Chunk, ChunkSettings, ChunkedWorld};
+pub use cycles::{CycleTemplate, EdgeKind, MissionEdge, MissionGraph, MissionRole};
+pub use difficulty::DifficultyBand;
+pub use distance::DistanceMap;
+pub use events::GenerationEvent;
+pub use graph::{Corridor, RoomGraph};
+pub use locks::{Key, Lock, LockSettings};
+pub use placement::{EntityKind, Placement, PlacementSettings, SpawnEntry, SpawnTable};
+pub use export::{
+    AnimationSettings, AsciiSettings, Colour, LdtkSettings, RenderSettings, SvgSettings,
+    TiledSettings, AREA_COLOURS, TRANSPARENT
+};
+pub use room::{Room, RoomGenerator, RoomKind};
+pub use rules::{AreaFilter, RoomPick, RoomRule};
+pub use stack::{DungeonStack, StackSettings};
+pub use stairs::StairsPlacement;
+pub use tile::TileKind;
 pub use tunnels::Tunneler;
+pub use validation::ValidationReport;
 
+// (area index, room index)
+pub type RoomRef = (usize, usize);
 
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Dungeon {
     pub tiles: HashSet<Vector2Int>,
     pub areas: Vec<Area>,
+    pub entrance: Option<Vector2Int>,
+    pub exit: Option<Vector2Int>,
+    // corridors joining the areas and the ones added by repair
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub corridors: Vec<Corridor>,
+    // tunnels dug by repair from a start tile outside of the rooms,
+    // with the room they lead to
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub start_tunnels: Vec<(RoomRef, Vec<Vector2Int>)>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub graph: RoomGraph,
+    // locks in the order they can be opened
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub locks: Vec<Lock>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub keys: Vec<Key>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub placements: Vec<Placement>,
     pub row_count: usize,
+    pub seed: u64,
     rows: Vec<Vec<usize>>
 }
+impl Default for Dungeon {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl Dungeon {
     pub fn new() -> Self {
-        let row_count = 2;
+        Dungeon::with_rows(2)
+    }
+    pub fn with_rows(row_count: usize) -> Self {
         let rows = (0..row_count).map(|_| Vec::new()).collect::<Vec<_>>();
         Dungeon { 
             row_count,
             tiles: HashSet::new(),
             areas: Vec::new(),
+            entrance: None,
+            exit: None,
+            corridors: Vec::new(),
+            start_tunnels: Vec::new(),
+            graph: RoomGraph::default(),
+            locks: Vec::new(),
+            keys: Vec::new(),
+            placements: Vec::new(),
+            seed: thread_rng().gen(),
             rows
         }
     }
@@ -35,25 +106,59 @@ impl Dungeon {
         // insert index to appropriate row table
         self.rows[idx % self.row_count].push(idx);
     }
-    fn get_dim(&self) -> Vector2Int {
-        // assumes that the areas have been shifted
-        let max_x = self.tiles.iter().map(|a| a.x).max().unwrap();
-        let max_y = self.tiles.iter().map(|a| a.y).max().unwrap();
-        Vector2Int::new(max_x, max_y)
+    pub fn get_rooms(&self) -> impl Iterator<Item=(RoomRef, &Room)> {
+        self.areas.iter()
+            .enumerate()
+            .flat_map(|(i, a)| a.rooms.iter().enumerate().map(move |(j, r)| ((i, j), r)))
     }
-    pub fn save_img(&self, path: &str, scale: u32) {
-        let size = self.get_dim();
-        let mut buf: image::RgbImage = ImageBuffer::new(size.x as u32 + 1, size.y as u32 + 1);
-
-        for (x, y, pixel) in buf.enumerate_pixels_mut() {
-            if self.tiles.contains(&Vector2Int::new(x as i32, y as i32)) {
-                *pixel = image::Rgb([150, 150, 50]);
-            } else {
-                *pixel = image::Rgb([0, 0, 0])
+    pub fn get_room(&self, room_ref: RoomRef) -> &Room {
+        &self.areas[room_ref.0].rooms[room_ref.1]
+    }
+    pub fn translate(&mut self, offset: Vector2Int) {
+        // move the whole generated dungeon, with everything placed in it
+        for area in self.areas.iter_mut() {
+            let bounds = area.get_bounds();
+            area.shift(bounds.0.x + offset.x, bounds.0.y + offset.y);
+        }
+        self.tiles = self.tiles.iter().map(|v| *v + offset).collect();
+        for corridor in self.corridors.iter_mut() {
+            corridor.path.iter_mut().for_each(|v| *v += offset);
+        }
+        for (_, path) in self.start_tunnels.iter_mut() {
+            path.iter_mut().for_each(|v| *v += offset);
+        }
+        self.entrance = self.entrance.map(|v| v + offset);
+        self.exit = self.exit.map(|v| v + offset);
+        self.locks.iter_mut().for_each(|l| l.tile += offset);
+        self.keys.iter_mut().for_each(|k| k.tile += offset);
+        self.placements.iter_mut().for_each(|p| p.tile += offset);
+        self.update_graph();
+    }
+    pub fn get_bounds(&self) -> Option<(Vector2Int, Vector2Int)> {
+        // None until the dungeon is generated
+        let min_x = self.tiles.iter().map(|a| a.x).min()?;
+        let max_x = self.tiles.iter().map(|a| a.x).max()?;
+        let min_y = self.tiles.iter().map(|a| a.y).min()?;
+        let max_y = self.tiles.iter().map(|a| a.y).max()?;
+        Some((Vector2Int::new(min_x, min_y), Vector2Int::new(max_x, max_y)))
+    }
+    fn get_padded_bounds(&self, margin: i32) -> (Vector2Int, Vector2Int) {
+        // bounds grown by the margin, for the maps and images
+        // with no tiles they hold nothing, so the output comes out empty
+        let m = Vector2Int::new(margin, margin);
+        self.get_bounds()
+            .map(|(a, b)| (a - m, b + m))
+            .unwrap_or((Vector2Int::new(0, 0), Vector2Int::new(-1, -1)))
+    }
+    fn assign_ids(&mut self) {
+        let mut id = 0;
+        for (i, area) in self.areas.iter_mut().enumerate() {
+            for room in area.rooms.iter_mut() {
+                room.area = i;
+                room.id = id;
+                id += 1;
             }
         }
-        let resized = image::imageops::resize(&buf, size.x as u32 * scale, size.y as u32 * scale, image::imageops::FilterType::Nearest);
-        resized.save(path).unwrap();
     }
     fn write_areas(&mut self) {
         // persist areas to tiles
@@ -66,23 +171,39 @@ impl Dungeon {
             }
         }
     }
-    fn connect_areas(&mut self) {
+    pub fn rebuild_tiles(&mut self) {
+        // tiles from the area data and the stored corridors only
+        self.tiles.clear();
+        self.write_areas();
+        for corridor in self.corridors.iter() {
+            self.tiles.extend(&corridor.path);
+        }
+        for (_, path) in self.start_tunnels.iter() {
+            self.tiles.extend(path);
+        }
+    }
+    fn get_area_links(&self) -> Vec<(usize, usize)> {
+        // (area, target) pairs joined by connect_areas, in the joining order
+        let mut links = Vec::new();
         for (y, row) in self.rows.iter().enumerate() {
             for (x, idx) in row.iter().enumerate() {
-                if x != 0 {
-                    // join to area at x - 1
-                    let target_idx = row[x-1];
-                    self.tiles.extend(&self.areas[*idx].join(&self.areas[target_idx]));
-                };
-                if y != 0 {
-                    // join to area at y - 1
-                    let target_idx = self.rows[y-1][x];
-                    self.tiles.extend(&self.areas[*idx].join(&self.areas[target_idx]));
-                };
+                // join to area at x - 1
+                if x != 0 { links.push((*idx, row[x-1])) }
+                // join to area at y - 1
+                if y != 0 { links.push((*idx, self.rows[y-1][x])) }
             }
         }
+        links
+    }
+    fn connect_areas(&mut self, rng: &mut StdRng, observer: &mut dyn FnMut(GenerationEvent)) {
+        for (idx, target_idx) in self.get_area_links() {
+            let ((a, b), path) = self.areas[idx].join(&self.areas[target_idx], rng);
+            self.tiles.extend(&path);
+            self.corridors.push(Corridor::new((idx, a), (target_idx, b), path.clone()));
+            observer(GenerationEvent::AreasConnected { from: idx, to: target_idx, path });
+        }
     }
-    fn position_areas(&mut self) {
+    fn position_areas(&mut self, observer: &mut dyn FnMut(GenerationEvent)) {
         let column_count = self.rows[0].len();
         let spacing = 4;
 
@@ -90,13 +211,13 @@ impl Dungeon {
         let column_widths = (0..column_count).map(|i| 
                 self.rows.iter().map(|r| match r.get(i) {
                     None => 0,
-                    Some(a) => self.areas[i].get_size().x
+                    Some(a) => self.areas[*a].get_size().x
                 }).max().unwrap() + spacing
             )
             .collect::<Vec<_>>();
         let row_heights = self.rows.iter()
             .map(|r| 
-                r.iter().map(|i| self.areas[*i].get_size().y).max().unwrap() + spacing
+                r.iter().map(|i| self.areas[*i].get_size().y).max().unwrap_or(0) + spacing
             )
             .collect::<Vec<_>>();
         let column_shifts = (0..column_widths.len())
@@ -109,16 +230,88 @@ impl Dungeon {
         // reposition areas
         for (y, row) in self.rows.iter().enumerate() {
             for (x, idx) in row.iter().enumerate() {
-                self.areas[*idx].shift(column_shifts[x], row_shifts[y]);
+                let offset = self.areas[*idx].shift(column_shifts[x], row_shifts[y]);
+                observer(GenerationEvent::AreaShifted { area: *idx, offset });
             }
         }
     }
     pub fn generate(&mut self) {
-        for area in self.areas.iter_mut() {
-            area.generate_rooms();
+        self.generate_with(&mut |_| ());
+    }
+    pub fn generate_with(&mut self, observer: &mut dyn FnMut(GenerationEvent)) {
+        // same as generate, but reports every step to the observer
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        // start over, so a dungeon can be generated again with a new seed
+        self.tiles.clear();
+        self.corridors.clear();
+        self.start_tunnels.clear();
+        self.entrance = None;
+        self.exit = None;
+        self.locks.clear();
+        self.keys.clear();
+        self.placements.clear();
+        for (i, area) in self.areas.iter_mut().enumerate() {
+            observer(GenerationEvent::AreaStarted(i));
+            area.generate_rooms(&mut rng, observer);
         }
-        self.position_areas();
+        self.assign_ids();
+        self.position_areas(observer);
         self.write_areas();
-        self.connect_areas();
+        self.connect_areas(&mut rng, observer);
+        self.update_graph();
+        (self.locks, self.keys) = self.get_mission_locks();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "serde")]
+    #[test]
+    fn dungeon_json_round_trip() {
+        use super::*;
+
+        let mut d = Dungeon::new();
+        d.seed = 5;
+        for strategy in [ConnectionStrategy::Cycle(CycleTemplate::LockKey), ConnectionStrategy::Secondary(4)] {
+            d.add_area(Area::new(
+                RoomGenerator::GrowSeparated { count: 6, min_size: 3, max_size: 5 },
+                Tunneler::LShape,
+                strategy
+            ));
+        }
+        d.generate();
+        d.place_stairs(1.);
+        d.place_locks(&LockSettings { locks: 2, keys_per_lock: 1, max_depth: 2, nested: false });
+
+        let json = serde_json::to_string(&d).unwrap();
+        let mut loaded: Dungeon = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.tiles, d.tiles);
+        assert_eq!((loaded.entrance, loaded.exit), (d.entrance, d.exit));
+        assert_eq!((loaded.seed, loaded.row_count), (d.seed, d.row_count));
+        for (a, b) in loaded.areas.iter().zip(d.areas.iter()) {
+            assert_eq!(a.rooms, b.rooms);
+            assert_eq!(a.paths, b.paths);
+            assert_eq!(a.connections, b.connections);
+            assert_eq!(a.mission, b.mission);
+        }
+        assert_eq!(loaded.corridors, d.corridors);
+        assert_eq!(loaded.start_tunnels, d.start_tunnels);
+        assert_eq!((&loaded.locks, &loaded.keys), (&d.locks, &d.keys));
+        assert_eq!(loaded.placements, d.placements);
+        // the graph's adjacency is rebuilt on load
+        assert_eq!(loaded.graph.edges, d.graph.edges);
+        for (r, _) in d.get_rooms() {
+            assert_eq!(loaded.graph.get_degree(r), d.graph.get_degree(r));
+        }
+        assert_eq!(loaded.to_ascii(&AsciiSettings::default()), d.to_ascii(&AsciiSettings::default()));
+
+        // the area rows come back too, so the loaded dungeon can be extended
+        loaded.add_area(Area::new(
+            RoomGenerator::Grow { count: 4, min_size: 3, max_size: 5 },
+            Tunneler::LShape,
+            ConnectionStrategy::Basic
+        ));
+        loaded.generate();
+        assert_eq!(loaded.areas.len(), 3);
     }
 }
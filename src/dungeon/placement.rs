@@ -0,0 +1,133 @@
+use rand::{distributions::WeightedIndex, prelude::*, rngs::StdRng};
+use std::collections::HashSet;
+
+use crate::vectors::{Vector2Int, ORTHO_DIRECTIONS};
+
+use super::{Dungeon, RoomKind, RoomRef};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EntityKind {
+    Monster,
+    Loot,
+    Trap,
+    Prop
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpawnEntry {
+    pub name: String,
+    pub kind: EntityKind,
+    pub weight: u32
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpawnTable {
+    pub entries: Vec<SpawnEntry>,
+    // entities per floor tile, eg. 0.05 is one per 20 tiles
+    pub density: f32,
+    // room kinds the table is used in, all when empty
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub room_kinds: Vec<RoomKind>,
+    // area indexes the table is used in, all when empty
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub areas: Vec<usize>,
+    // (min, max) room difficulty the table is used at, all when None
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub difficulty: Option<(f32, f32)>
+}
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlacementSettings {
+    pub tables: Vec<SpawnTable>,
+    // min manhattan distance between two entities
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min_spacing: i32,
+    // min manhattan distance from the entrance
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub start_spacing: i32
+}
+impl PlacementSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, table) in self.tables.iter().enumerate() {
+            if table.entries.iter().all(|e| e.weight == 0) {
+                return Err(format!("spawn table {} has no entries with a weight above 0", i))
+            }
+            if table.density.is_nan() || table.density < 0. {
+                return Err(format!("spawn table {} has an invalid density", i))
+            }
+            if table.difficulty.is_some_and(|(min, max)| min.is_nan() || max.is_nan() || min > max) {
+                return Err(format!("spawn table {} has an invalid difficulty range", i))
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Placement {
+    pub name: String,
+    pub kind: EntityKind,
+    pub tile: Vector2Int,
+    pub room: RoomRef
+}
+
+impl Dungeon {
+    pub fn get_placements(&self, settings: &PlacementSettings) -> Vec<Placement> {
+        // entities are only placed on room tiles, away from the doors,
+        // so the corridors and the ways in stay clear
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let doors = self.get_doors();
+        let mut blocked = doors.iter()
+            .flat_map(|v| ORTHO_DIRECTIONS.iter().map(move |d| *v + *d))
+            .collect::<HashSet<_>>();
+        blocked.extend(self.entrance);
+        blocked.extend(self.exit);
+        blocked.extend(self.locks.iter().map(|l| l.tile));
+        blocked.extend(self.keys.iter().map(|k| k.tile));
+
+        let difficulties = self.get_difficulties();
+        let mut placements: Vec<Placement> = Vec::new();
+        for (room_ref, room) in self.get_rooms() {
+            let difficulty = difficulties[&room_ref];
+            let tables = settings.tables.iter()
+                .filter(|t| t.room_kinds.is_empty() || t.room_kinds.contains(&room.kind))
+                .filter(|t| t.areas.is_empty() || t.areas.contains(&room_ref.0))
+                .filter(|t| t.difficulty.is_none_or(|(min, max)| difficulty >= min && difficulty <= max));
+
+            for table in tables {
+                let Ok(weights) = WeightedIndex::new(table.entries.iter().map(|e| e.weight)) else { continue };
+                // the fraction is used as a chance for one more entity
+                let expected = table.density * room.get_floor_area() as f32;
+                let mut count = expected as usize;
+                if rng.gen::<f32>() < expected.fract() { count += 1 }
+
+                let mut tiles = room.get_tiles().into_iter()
+                    .filter(|v| !blocked.contains(v))
+                    .filter(|v| self.entrance.is_none_or(|e| e.manhattan(*v) >= settings.start_spacing))
+                    .collect::<Vec<_>>();
+                tiles.sort_by_key(|v| (v.y, v.x));
+                tiles.shuffle(&mut rng);
+
+                let mut placed = 0;
+                for tile in tiles {
+                    if placed == count { break }
+                    if placements.iter().any(|p| p.tile.manhattan(tile) < settings.min_spacing.max(1)) { continue }
+                    let entry = &table.entries[weights.sample(&mut rng)];
+                    placements.push(Placement { name: entry.name.clone(), kind: entry.kind, tile, room: room_ref });
+                    placed += 1;
+                }
+            }
+        }
+        placements
+    }
+    pub fn place_entities(&mut self, settings: &PlacementSettings) -> usize {
+        // returns the number of placed entities
+        self.placements = self.get_placements(settings);
+        self.placements.len()
+    }
+}
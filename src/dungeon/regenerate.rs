@@ -0,0 +1,246 @@
+use rand::{prelude::*, rngs::StdRng};
+use std::collections::HashSet;
+
+use crate::vectors::Vector2Int;
+
+use super::{Corridor, Dungeon, Key, Lock};
+use super::locks::merge_locks;
+
+// rolls of the area's generator, until its rooms don't run into the other areas
+const ATTEMPTS: usize = 10;
+
+// graph corridor, as an area connection or an index into Dungeon::corridors
+enum CorridorRef {
+    Area(usize, usize),
+    Joint(usize)
+}
+
+impl Dungeon {
+    pub fn regenerate_area(&mut self, idx: usize) -> bool {
+        self.regenerate_area_with_seed(idx, thread_rng().gen())
+    }
+    pub fn regenerate_area_with_seed(&mut self, idx: usize, seed: u64) -> bool {
+        // re-rolls a single area at its old position, the other areas keep their layout
+        // the joins to the neighbouring areas are redone, repair corridors leading
+        // to the area are dropped, as are the locks and keys touching it
+        // and the locks the new tunnels lead around
+        // returns false (and keeps the old area) when no layout fits in between the others
+        let mut rng = StdRng::seed_from_u64(seed);
+        let origin = self.areas[idx].get_bounds().0;
+        let others = self.areas.iter()
+            .enumerate()
+            .filter(|(i, _)| *i != idx)
+            .map(|(_, a)| a.get_bounds())
+            .collect::<Vec<_>>();
+        // the new rooms get ids above all the current ones
+        let next_id = self.get_rooms().map(|(_, r)| r.id + 1).max().unwrap_or(0);
+
+        let area = &mut self.areas[idx];
+        let old = (area.rooms.clone(), area.paths.clone(), area.connections.clone(), area.mission.clone());
+        let mut fits = false;
+        for _ in 0..ATTEMPTS {
+            area.generate_rooms(&mut rng, &mut |_| ());
+            area.shift(origin.x, origin.y);
+            let (a, b) = area.get_bounds();
+            // keep a free tile between the areas
+            let margin = Vector2Int::new(1, 1);
+            fits = others.iter().all(|(oa, ob)| {
+                a.x > ob.x + margin.x || b.x < oa.x - margin.x || a.y > ob.y + margin.y || b.y < oa.y - margin.y
+            });
+            if fits { break }
+        }
+        if !fits {
+            (area.rooms, area.paths, area.connections, area.mission) = old;
+            return false
+        }
+        for (i, room) in area.rooms.iter_mut().enumerate() {
+            room.area = idx;
+            room.id = next_id + i;
+        }
+
+        // anything placed in the old area is gone
+        let old_tiles = old.0.iter().flat_map(|r| r.get_tiles()).collect::<HashSet<_>>();
+        if self.entrance.is_some_and(|v| old_tiles.contains(&v)) { self.entrance = None }
+        if self.exit.is_some_and(|v| old_tiles.contains(&v)) { self.exit = None }
+        self.placements.retain(|p| p.room.0 != idx);
+        self.start_tunnels.retain(|(r, _)| r.0 != idx);
+
+        // locks point at graph corridors, which are about to be renumbered
+        let old_counts = self.areas.iter()
+            .enumerate()
+            .map(|(i, a)| if i == idx { old.2.len() } else { a.connections.len() })
+            .collect::<Vec<_>>();
+        let lock_corridors = self.locks.iter()
+            .map(|l| get_corridor_ref(l.corridor, &old_counts))
+            .collect::<Vec<_>>();
+        let kept = (0..self.corridors.len())
+            .filter(|i| self.corridors[*i].a.0 != idx && self.corridors[*i].b.0 != idx)
+            .collect::<Vec<_>>();
+
+        self.corridors.retain(|c| c.a.0 != idx && c.b.0 != idx);
+        for (from, to) in self.get_area_links() {
+            if from != idx && to != idx { continue }
+            let ((a, b), path) = self.areas[from].join(&self.areas[to], &mut rng);
+            self.corridors.push(Corridor::new((from, a), (to, b), path));
+        }
+        self.remap_locks(idx, &lock_corridors, &kept);
+
+        self.rebuild_tiles();
+        self.update_graph();
+        self.drop_bypassed_locks();
+        self.add_mission_locks(idx);
+        // the kept keys can end up out of reach, the mission locks are left then
+        if self.entrance.is_some_and(|v| !self.is_solvable(v, &self.locks, &self.keys)) {
+            (self.locks, self.keys) = self.get_mission_locks();
+            self.update_lock_depths();
+        }
+        true
+    }
+    fn remap_locks(&mut self, idx: usize, lock_corridors: &[CorridorRef], kept: &[usize]) {
+        // drops the locks placed in the area or with a key in it, renumbers the rest
+        let counts = self.areas.iter().map(|a| a.connections.len()).collect::<Vec<_>>();
+        let area_total = counts.iter().sum::<usize>();
+        let mut new_idxs = Vec::new();
+        let mut locks = Vec::new();
+        for (i, (lock, corridor)) in self.locks.iter().zip(lock_corridors).enumerate() {
+            let corridor = match corridor {
+                CorridorRef::Area(a, j) if *a != idx => Some(counts[..*a].iter().sum::<usize>() + j),
+                CorridorRef::Joint(k) => kept.iter().position(|c| c == k).map(|p| area_total + p),
+                _ => None
+            };
+            let key_inside = self.keys.iter().any(|k| k.lock == i && k.room.0 == idx);
+            match corridor {
+                Some(corridor) if lock.room.0 != idx && !key_inside => {
+                    new_idxs.push(Some(locks.len()));
+                    locks.push(Lock { corridor, ..lock.clone() });
+                },
+                _ => new_idxs.push(None)
+            }
+        }
+        self.keys = self.keys.iter()
+            .filter_map(|k| Some(Key { lock: new_idxs[k.lock]?, ..k.clone() }))
+            .collect();
+        self.locks = locks;
+    }
+    fn add_mission_locks(&mut self, idx: usize) {
+        // the lock-key cycle of the new area, if it has one
+        let (mission_locks, mission_keys) = self.get_mission_locks();
+        let picked = (0..mission_locks.len())
+            .filter(|i| mission_locks[*i].room.0 == idx)
+            .collect::<Vec<_>>();
+        let locks = picked.iter().map(|i| mission_locks[*i].clone()).collect();
+        let keys = mission_keys.into_iter()
+            .filter_map(|k| Some(Key { lock: picked.iter().position(|i| *i == k.lock)?, ..k }))
+            .collect();
+        let current = (std::mem::take(&mut self.locks), std::mem::take(&mut self.keys));
+        (self.locks, self.keys) = merge_locks(current, (locks, keys));
+        self.update_lock_depths();
+    }
+}
+
+fn get_corridor_ref(corridor: usize, counts: &[usize]) -> CorridorRef {
+    // graph corridors start with the area connections, see update_graph
+    let mut rest = corridor;
+    for (i, count) in counts.iter().enumerate() {
+        if rest < *count { return CorridorRef::Area(i, rest) }
+        rest -= count;
+    }
+    CorridorRef::Joint(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dungeon::{
+        Area, ConnectionStrategy, CycleTemplate, LockSettings, RoomGenerator, RoomRef, Tunneler
+    };
+    use crate::vectors::{find_path, ORTHO_DIRECTIONS};
+    use super::*;
+
+    fn get_dungeon(seed: u64) -> Dungeon {
+        let mut d = Dungeon::new();
+        d.seed = seed;
+        let strategies = [
+            ConnectionStrategy::Basic,
+            ConnectionStrategy::Cycle(CycleTemplate::LockKey),
+            ConnectionStrategy::Basic,
+            ConnectionStrategy::Secondary(4)
+        ];
+        for strategy in strategies {
+            d.add_area(Area::new(
+                RoomGenerator::GrowSeparated { count: 6, min_size: 3, max_size: 5 },
+                Tunneler::LShape,
+                strategy
+            ));
+        }
+        d.generate();
+        // a start tile away from the rooms, so repair has to tunnel from it
+        let start = d.get_bounds().unwrap().0 - Vector2Int::new(3, 3);
+        d.repair(start);
+        d.place_stairs(1.);
+        d.place_locks(&LockSettings { locks: 3, keys_per_lock: 1, max_depth: 3, nested: false });
+        d
+    }
+
+    #[test]
+    fn regenerate_keeps_the_other_areas() {
+        let mut regenerated = 0;
+        for seed in 0..12 {
+            let mut d = get_dungeon(seed);
+            let idx = seed as usize % d.areas.len();
+            let ids = d.get_rooms().map(|(r, room)| (r, room.id)).collect::<Vec<_>>();
+            let max_id = ids.iter().map(|(_, id)| *id).max().unwrap();
+            let kept_locks = d.locks.iter()
+                .enumerate()
+                .filter(|(i, l)| l.room.0 != idx && !d.keys.iter().any(|k| k.lock == *i && k.room.0 == idx))
+                .filter(|(_, l)| d.graph.edges[l.corridor].a.0 != idx && d.graph.edges[l.corridor].b.0 != idx)
+                .map(|(_, l)| l.clone())
+                .collect::<Vec<_>>();
+            let tunnels = d.start_tunnels.iter()
+                .filter(|(r, _)| r.0 != idx)
+                .flat_map(|(_, p)| p.iter().copied())
+                .collect::<Vec<_>>();
+
+            if !d.regenerate_area_with_seed(idx, seed) { continue }
+            regenerated += 1;
+
+            // ids of the other rooms stay, the new ones are fresh
+            for ((r, room), (old_r, id)) in d.get_rooms().filter(|(r, _)| r.0 != idx).zip(ids.iter().filter(|(r, _)| r.0 != idx)) {
+                assert_eq!((r, room.id), (*old_r, *id));
+            }
+            assert!(d.areas[idx].rooms.iter().all(|r| r.id > max_id && r.area == idx));
+
+            // locks away from the area are kept unless the new tunnels lead around them,
+            // and still sit on their corridors
+            let only_missions = d.locks == d.get_mission_locks().0;
+            for lock in kept_locks {
+                // a dropped lock had a way around it, or its keys were out of reach
+                if !d.locks.iter().any(|l| l.tile == lock.tile) {
+                    assert!(!d.is_cut_off(&lock) || only_missions, "seed {}", seed);
+                }
+            }
+            for lock in d.locks.iter() {
+                assert!(d.graph.edges[lock.corridor].path.contains(&lock.tile), "seed {}", seed);
+                // every kept lock still cuts off its room
+                let closed = HashSet::from([lock.tile]);
+                let room = d.get_room(lock.room).centre();
+                let outside = ORTHO_DIRECTIONS.iter().map(|dir| lock.tile + *dir).filter(|v| d.tiles.contains(v));
+                assert!(outside.into_iter().any(|v| find_path(room, v, &d.tiles, &closed).is_none()), "seed {}", seed);
+            }
+            if let Some(entrance) = d.entrance {
+                assert!(d.is_solvable(entrance, &d.locks, &d.keys), "seed {}", seed);
+            }
+            let keys_in_area = d.keys.iter().filter(|k| k.room.0 == idx).collect::<Vec<_>>();
+            for key in d.keys.iter() {
+                assert!(key.lock < d.locks.len());
+            }
+            // only a mission lock of the new area can be placed in it
+            let mission_rooms: Vec<RoomRef> = d.get_mission_locks().0.iter().map(|l| l.room).collect();
+            assert!(d.locks.iter().filter(|l| l.room.0 == idx).all(|l| mission_rooms.contains(&l.room)));
+            assert!(keys_in_area.iter().all(|k| mission_rooms.contains(&d.locks[k.lock].room)));
+
+            // the repair tunnels to the other areas survive the rebuild
+            assert!(tunnels.iter().all(|v| d.tiles.contains(v)), "seed {}", seed);
+        }
+        assert!(regenerated > 0);
+    }
+}
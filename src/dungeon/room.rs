@@ -1,42 +1,69 @@
-use rand::prelude::*;
-use std::collections::HashSet;
+use rand::{prelude::*, rngs::StdRng};
+use std::collections::{HashMap, HashSet};
 
-use crate::vectors::Vector2Int;
+use crate::vectors::{poisson_disk_sample, Vector2Int};
 
+use super::events::GenerationEvent;
 use super::tunnels::Tunneler;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Room {
     pub a: Vector2Int,
-    pub b: Vector2Int
+    pub b: Vector2Int,
+    // unique within the dungeon, assigned on generation
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub id: usize,
+    // index of the owning area
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub area: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kind: RoomKind,
+    // free-form data for the content pipeline
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub properties: HashMap<String, String>
 }
 impl Room {
     pub fn new(a: Vector2Int, b: Vector2Int) -> Self {
         Room {
             a: Vector2Int::new(a.x.min(b.x), a.y.min(b.y)),
-            b: Vector2Int::new(a.x.max(b.x), a.y.max(b.y))
+            b: Vector2Int::new(a.x.max(b.x), a.y.max(b.y)),
+            id: 0,
+            area: 0,
+            kind: RoomKind::default(),
+            properties: HashMap::new()
         }
     }
+    pub fn get_floor_area(&self) -> i32 {
+        (self.b.x - self.a.x + 1) * (self.b.y - self.a.y + 1)
+    }
     pub fn corners(&self) -> [Vector2Int; 4] {
         [
             Vector2Int::new(self.a.x, self.a.y), Vector2Int::new(self.b.x, self.a.y),
             Vector2Int::new(self.b.x, self.b.y), Vector2Int::new(self.a.x, self.b.y)
         ]
     }
-    pub fn random_point(&self) -> Vector2Int {
-        let mut rng = thread_rng();
+    pub fn random_point(&self, rng: &mut StdRng) -> Vector2Int {
         let x = rng.gen_range(self.a.x..=self.b.x);
         let y = rng.gen_range(self.a.y..=self.b.y);
         Vector2Int::new(x, y)
     }
+    pub fn sample_points(&self, radius: f32, max_attempts: usize, rng: &mut StdRng) -> Vec<Vector2Int> {
+        // evenly spread points, no two closer than the radius
+        poisson_disk_sample(&self.get_tiles(), radius, max_attempts, rng)
+    }
     pub fn centre(&self) -> Vector2Int {
         Vector2Int::new((self.b.x+self.a.x) / 2, (self.b.y+self.a.y) / 2)
     }
+    pub fn corner_distance(&self, other: &Room) -> i32 {
+        // min manhattan distance between the corners of two rooms
+        self.corners().iter()
+            .flat_map(|ca| other.corners().iter().map(|cb| ca.manhattan(*cb)).collect::<Vec<_>>())
+            .min()
+            .unwrap()
+    }
     pub fn intersects(&self, other: &Room, border: Option<i32>) -> bool {
-        let b = match border {
-            Some(a) => a,
-            None => 0
-        };
+        let b = border.unwrap_or_default();
         !(
             other.a.x > self.b.x + b ||
             other.b.x < self.a.x - b ||
@@ -44,25 +71,57 @@ impl Room {
             other.b.y < self.a.y - b
         )
     }
-    pub fn join(&self, other: &Room, tunneler: &Tunneler) -> Vec<Vector2Int> {
+    pub fn join(&self, other: &Room, tunneler: &Tunneler, rng: &mut StdRng) -> Vec<Vector2Int> {
         // make a connection between two rooms
-        let va = self.random_point();
-        let vb = other.random_point();
-        tunneler.get_connector()(va, vb)
+        let va = self.random_point(rng);
+        let vb = other.random_point(rng);
+        tunneler.get_connector()(va, vb, rng)
     }
     pub fn get_tiles(&self) -> HashSet<Vector2Int> {
-        (self.a.y..=self.b.y).map(|y| {
+        (self.a.y..=self.b.y).flat_map(|y| {
                 (self.a.x..=self.b.x).map(move |x| {
                     Vector2Int::new(x, y)
                 })
             })
-            .flatten()
             .collect()
     }
 }
 
-pub type GeneratorFunc<'a> = Box<dyn Fn() -> (Vec<Room>, Vec<(usize, usize)>) + 'a>;
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RoomKind {
+    #[default]
+    Normal,
+    Entrance,
+    Exit,
+    Treasure,
+    Boss,
+    Shop,
+    // a room joining at least three corridors
+    Junction,
+    Custom(String)
+}
+impl RoomKind {
+    pub fn get_name(&self) -> &str {
+        match self {
+            Self::Normal => "normal",
+            Self::Entrance => "entrance",
+            Self::Exit => "exit",
+            Self::Treasure => "treasure",
+            Self::Boss => "boss",
+            Self::Shop => "shop",
+            Self::Junction => "junction",
+            Self::Custom(name) => name
+        }
+    }
+}
+
+pub type GeneratorFunc<'a> = Box<
+    dyn Fn(&mut StdRng, &mut dyn FnMut(GenerationEvent)) -> (Vec<Room>, Vec<(usize, usize)>) + 'a
+>;
 
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RoomGenerator {
     Chamber { min_size: u32, max_size: u32 },
     Grow { count: u32, min_size: u32, max_size: u32 },
@@ -70,34 +129,49 @@ pub enum RoomGenerator {
 }
 impl RoomGenerator {
     // returns a vec of rooms and a vec of connection indexes
-    pub fn get_generator<'a>(&'a self) -> GeneratorFunc {
+    pub fn get_generator(&self) -> GeneratorFunc<'_> {
         match self {
-            Self::Grow {count, min_size, max_size } => Box::new(|| {
-                grow_generator(*count, *min_size, *max_size, None)
+            Self::Grow {count, min_size, max_size } => Box::new(|rng: &mut StdRng, observer: &mut dyn FnMut(GenerationEvent)| {
+                grow_generator(*count, *min_size, *max_size, None, rng, observer)
             }) as GeneratorFunc,
-            Self::GrowSeparated {count, min_size, max_size } => Box::new(|| {
-                grow_generator(*count, *min_size, *max_size, Some(3))
+            Self::GrowSeparated {count, min_size, max_size } => Box::new(|rng: &mut StdRng, observer: &mut dyn FnMut(GenerationEvent)| {
+                grow_generator(*count, *min_size, *max_size, Some(3), rng, observer)
             }) as GeneratorFunc,
-            Self::Chamber { min_size, max_size } => Box::new(|| {
-                chamber_generator(*min_size, *max_size)
+            Self::Chamber { min_size, max_size } => Box::new(|rng: &mut StdRng, observer: &mut dyn FnMut(GenerationEvent)| {
+                chamber_generator(*min_size, *max_size, rng, observer)
             }) as GeneratorFunc
         }
     }
+    pub fn validate(&self) -> Result<(), String> {
+        // check the parameter ranges, so the generators can't panic or loop forever
+        let (count, min_size, max_size) = match self {
+            Self::Grow { count, min_size, max_size } => (*count, min_size, max_size),
+            Self::GrowSeparated { count, min_size, max_size } => (*count, min_size, max_size),
+            Self::Chamber { min_size, max_size } => (1, min_size, max_size)
+        };
+        if count == 0 { return Err("room count has to be at least 1".to_string()) }
+        if min_size > max_size {
+            return Err(format!("min_size ({}) is larger than max_size ({})", min_size, max_size))
+        }
+        Ok(())
+    }
 }
 
-pub fn chamber_generator(min_size: u32, max_size: u32)
--> (Vec<Room>, Vec<(usize, usize)>) {
-    let w = get_random_dim(min_size, max_size);
-    let h = get_random_dim(min_size, max_size);
+pub fn chamber_generator(
+    min_size: u32, max_size: u32, rng: &mut StdRng, observer: &mut dyn FnMut(GenerationEvent)
+) -> (Vec<Room>, Vec<(usize, usize)>) {
+    let w = get_random_dim(min_size, max_size, rng);
+    let h = get_random_dim(min_size, max_size, rng);
 
     let chamber = Room::new(Vector2Int::new(0,0), Vector2Int::new(w, h));
+    observer(GenerationEvent::RoomPlaced(chamber.clone()));
     (vec![chamber], Vec::new())
 }
 
 pub fn grow_generator(
-    count: u32, min_size: u32, max_size: u32, room_border: Option<i32>
+    count: u32, min_size: u32, max_size: u32, room_border: Option<i32>, rng: &mut StdRng,
+    observer: &mut dyn FnMut(GenerationEvent)
 ) -> (Vec<Room>, Vec<(usize, usize)>) {
-    let mut rng = thread_rng();
     let mut connections = Vec::new();
 
     // bounds const for searching new room's corner around the base room
@@ -109,8 +183,9 @@ pub fn grow_generator(
     // first room
     let mut rooms = vec![Room::new(
         Vector2Int::new(0, 0),
-        Vector2Int::new(get_random_dim(min_size, max_size), get_random_dim(min_size, max_size))
+        Vector2Int::new(get_random_dim(min_size, max_size, rng), get_random_dim(min_size, max_size, rng))
     )];
+    observer(GenerationEvent::RoomPlaced(rooms[0].clone()));
 
     for _ in 0..count - 1 {
         loop {
@@ -123,31 +198,35 @@ pub fn grow_generator(
 
             // find a direction for the second room corner (outwards from the reference room)
             let mut dv = (a - c).clamped();
-            if dv.x == 0 { dv.x = *[-1, 1].choose(&mut rng).unwrap() }
-            if dv.y == 0 { dv.y = *[-1, 1].choose(&mut rng).unwrap() }
+            if dv.x == 0 { dv.x = *[-1, 1].choose(rng).unwrap() }
+            if dv.y == 0 { dv.y = *[-1, 1].choose(rng).unwrap() }
 
             // get second corner
-            let w = get_random_dim(min_size, max_size);
-            let h = get_random_dim(min_size, max_size);
+            let w = get_random_dim(min_size, max_size, rng);
+            let h = get_random_dim(min_size, max_size, rng);
             let b = a + Vector2Int::new(dv.x * w, dv.y * h);
 
             let r = Room::new(a, b);
             // if the room overlaps another generate it again
-            if rooms.iter().any(|other| r.intersects(other, room_border)) { continue };
+            if rooms.iter().any(|other| r.intersects(other, room_border)) {
+                observer(GenerationEvent::RoomRejected(r));
+                continue
+            };
 
             // add a connection to the base room
             let cur_idx = rooms.len();
             connections.push((prev_idx, cur_idx));
 
             // room is valid, push it and break the loop
+            observer(GenerationEvent::RoomPlaced(r.clone()));
             rooms.push(r);
             break;
         }    
     }
+
     (rooms, connections)
 }
 
-fn get_random_dim(min: u32, max: u32) -> i32 {
-    let mut rng = thread_rng();
+fn get_random_dim(min: u32, max: u32, rng: &mut StdRng) -> i32 {
     rng.gen_range(min..=max) as i32
 }
\ No newline at end of file
@@ -0,0 +1,98 @@
+use rand::{prelude::*, rngs::StdRng};
+use std::{cmp::Reverse, collections::HashMap};
+
+use crate::vectors::get_distances;
+
+use super::{Dungeon, RoomKind};
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AreaFilter {
+    Index(usize),
+    First,
+    Last
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RoomPick {
+    #[default]
+    Largest,
+    Smallest,
+    // by walking distance from the entrance
+    Farthest,
+    Closest,
+    Random
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoomRule {
+    pub kind: RoomKind,
+    // all areas when None
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub area: Option<AreaFilter>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pick: RoomPick,
+    // number of rooms to tag
+    #[cfg_attr(feature = "serde", serde(default = "default_count"))]
+    pub count: usize,
+    // added to the picked rooms' properties
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub properties: HashMap<String, String>
+}
+impl RoomRule {
+    pub fn new(kind: RoomKind, area: Option<AreaFilter>, pick: RoomPick) -> Self {
+        RoomRule { kind, area, pick, count: 1, properties: HashMap::new() }
+    }
+}
+
+#[cfg(feature = "serde")]
+fn default_count() -> usize { 1 }
+
+impl Dungeon {
+    pub fn assign_rooms(&mut self, rules: &[RoomRule]) {
+        // tag rooms by rules, applied in order
+        // only normal and junction rooms can be picked, so earlier rules
+        // (and the stairs) are never overwritten
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut distances = None;
+
+        for rule in rules {
+            let area = match rule.area {
+                None => None,
+                Some(AreaFilter::Index(i)) => Some(i),
+                Some(AreaFilter::First) => Some(0),
+                Some(AreaFilter::Last) => Some(self.areas.len().saturating_sub(1))
+            };
+            let mut candidates = self.get_rooms()
+                .filter(|(r, _)| area.is_none() || area == Some(r.0))
+                .filter(|(_, room)| matches!(room.kind, RoomKind::Normal | RoomKind::Junction))
+                .map(|(r, room)| (r, room.get_floor_area(), room.centre()))
+                .collect::<Vec<_>>();
+
+            match rule.pick {
+                RoomPick::Largest => candidates.sort_by_key(|c| Reverse(c.1)),
+                RoomPick::Smallest => candidates.sort_by_key(|c| c.1),
+                RoomPick::Farthest | RoomPick::Closest => {
+                    let distances: &HashMap<_, _> = distances.get_or_insert_with(||
+                        self.entrance.map(|v| get_distances(v, &self.tiles)).unwrap_or_default()
+                    );
+                    // rooms unreachable from the entrance are skipped
+                    candidates.retain(|c| distances.contains_key(&c.2));
+                    match rule.pick {
+                        RoomPick::Farthest => candidates.sort_by_key(|c| Reverse(distances[&c.2])),
+                        _ => candidates.sort_by_key(|c| distances[&c.2])
+                    }
+                },
+                RoomPick::Random => candidates.shuffle(&mut rng)
+            }
+
+            for (r, _, _) in candidates.into_iter().take(rule.count) {
+                let room = &mut self.areas[r.0].rooms[r.1];
+                room.kind = rule.kind.clone();
+                room.properties.extend(rule.properties.clone());
+            }
+        }
+    }
+}
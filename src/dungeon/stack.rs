@@ -0,0 +1,77 @@
+use crate::vectors::Vector2Int;
+
+use super::Dungeon;
+
+pub struct StackSettings {
+    // max manhattan distance between a down-stair and the up-stair below it
+    // a floor without a room that close is moved under the stair
+    pub max_offset: i32,
+    // floors that can't fit the stairs are generated again with the next seed
+    pub attempts: usize,
+    // passed to the stairs placement, 1. puts the down-stair in the farthest room
+    pub percentile: f32
+}
+impl Default for StackSettings {
+    fn default() -> Self {
+        StackSettings { max_offset: 3, attempts: 20, percentile: 1. }
+    }
+}
+
+// floors ordered top to bottom
+// the exit of a floor is its down-stair, the entrance its up-stair
+#[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DungeonStack {
+    pub floors: Vec<Dungeon>
+}
+impl DungeonStack {
+    pub fn new() -> Self {
+        DungeonStack { floors: Vec::new() }
+    }
+    pub fn add_floor(&mut self, floor: Dungeon) {
+        self.floors.push(floor);
+    }
+    pub fn generate(&mut self, settings: &StackSettings) -> Result<(), String> {
+        // generates the floors one by one, so every up-stair is placed
+        // next to the down-stair of the floor above
+        let mut above: Option<Vector2Int> = None;
+        for (i, floor) in self.floors.iter_mut().enumerate() {
+            let mut placed = false;
+            for _ in 0..settings.attempts.max(1) {
+                floor.generate();
+                let start = floor.areas[0].rooms[0].centre();
+                floor.repair(start);
+                placed = match above {
+                    None => floor.place_stairs(settings.percentile).is_some(),
+                    Some(v) => get_up_stair(floor, v, settings.max_offset)
+                        .and_then(|e| floor.place_stairs_from(e, settings.percentile))
+                        .is_some()
+                };
+                if placed { break }
+                floor.seed = floor.seed.wrapping_add(1);
+            }
+            if !placed {
+                return Err(format!("floor {}: no stairs could be placed", i))
+            }
+            above = floor.exit;
+        }
+        Ok(())
+    }
+    pub fn get_stairs(&self) -> Vec<(Vector2Int, Vector2Int)> {
+        // (down-stair, up-stair) pairs between consecutive floors
+        self.floors.windows(2)
+            .filter_map(|f| Some((f[0].exit?, f[1].entrance?)))
+            .collect()
+    }
+}
+
+fn get_up_stair(floor: &mut Dungeon, target: Vector2Int, max_offset: i32) -> Option<Vector2Int> {
+    // the room tile closest to the stair above
+    // when it's too far the floor is moved, so the tile ends up right below
+    let tile = floor.get_rooms()
+        .flat_map(|(_, r)| r.get_tiles())
+        .min_by_key(|v| (v.manhattan(target), v.y, v.x))?;
+    if tile.manhattan(target) <= max_offset { return Some(tile) }
+    floor.translate(target - tile);
+    Some(target)
+}
@@ -0,0 +1,91 @@
+use crate::vectors::{get_distances, Vector2Int};
+
+use super::{Dungeon, RoomKind, RoomRef};
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StairsPlacement {
+    pub start_room: RoomRef,
+    pub exit_room: RoomRef,
+    pub entrance: Vector2Int,
+    pub exit: Vector2Int,
+    // walking distance between the entrance and the exit
+    pub distance: u32
+}
+
+impl Dungeon {
+    pub fn get_stairs(&self, percentile: f32) -> Option<StairsPlacement> {
+        // pick a room pair by walking distance between their centres
+        // percentile of 1. gives the most distant pair (graph diameter)
+        // the entrance is kept out of the rooms sealed by a lock
+        let locked = self.locks.iter().map(|l| l.room).collect::<Vec<_>>();
+        let rooms = self.get_rooms()
+            .map(|(r, room)| (r, room.centre()))
+            .collect::<Vec<_>>();
+
+        let mut pairs = Vec::new();
+        for (i, (ra, va)) in rooms.iter().enumerate() {
+            let distances = get_distances(*va, &self.tiles);
+            for (rb, vb) in rooms[i+1..].iter() {
+                let Some(d) = distances.get(vb) else { continue };
+                match (locked.contains(ra), locked.contains(rb)) {
+                    (false, _) => pairs.push((*d, (*ra, *va), (*rb, *vb))),
+                    (true, false) => pairs.push((*d, (*rb, *vb), (*ra, *va))),
+                    _ => ()
+                }
+            }
+        }
+        if pairs.is_empty() { return None }
+
+        pairs.sort_by_key(|p| p.0);
+        let idx = ((pairs.len() - 1) as f32 * percentile.clamp(0., 1.)).round() as usize;
+        let (distance, start, exit) = pairs[idx];
+        Some(StairsPlacement {
+            start_room: start.0,
+            exit_room: exit.0,
+            entrance: start.1,
+            exit: exit.1,
+            distance
+        })
+    }
+    pub fn get_stairs_from(&self, entrance: Vector2Int, percentile: f32) -> Option<StairsPlacement> {
+        // keep the given entrance (has to be inside a room) and pick the exit room
+        // by walking distance, 1. gives the most distant one
+        let start_room = self.get_rooms().find(|(_, r)| r.get_tiles().contains(&entrance))?.0;
+        let distances = get_distances(entrance, &self.tiles);
+        let mut exits = self.get_rooms()
+            .filter(|(r, _)| *r != start_room)
+            .filter_map(|(r, room)| Some((*distances.get(&room.centre())?, r, room.centre())))
+            .collect::<Vec<_>>();
+        if exits.is_empty() { return None }
+
+        exits.sort_by_key(|e| e.0);
+        let idx = ((exits.len() - 1) as f32 * percentile.clamp(0., 1.)).round() as usize;
+        let (distance, exit_room, exit) = exits[idx];
+        Some(StairsPlacement { start_room, exit_room, entrance, exit, distance })
+    }
+    pub fn place_stairs(&mut self, percentile: f32) -> Option<StairsPlacement> {
+        // also tags the rooms, replacing a previous placement
+        let stairs = self.get_stairs(percentile)?;
+        self.set_stairs(&stairs);
+        Some(stairs)
+    }
+    pub fn place_stairs_from(&mut self, entrance: Vector2Int, percentile: f32) -> Option<StairsPlacement> {
+        let stairs = self.get_stairs_from(entrance, percentile)?;
+        self.set_stairs(&stairs);
+        Some(stairs)
+    }
+    fn set_stairs(&mut self, stairs: &StairsPlacement) {
+        self.entrance = Some(stairs.entrance);
+        self.exit = Some(stairs.exit);
+
+        for area in self.areas.iter_mut() {
+            for room in area.rooms.iter_mut() {
+                if room.kind == RoomKind::Entrance || room.kind == RoomKind::Exit {
+                    room.kind = RoomKind::Normal;
+                }
+            }
+        }
+        self.areas[stairs.start_room.0].rooms[stairs.start_room.1].kind = RoomKind::Entrance;
+        self.areas[stairs.exit_room.0].rooms[stairs.exit_room.1].kind = RoomKind::Exit;
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::HashSet;
+
+use crate::vectors::{Grid, Vector2Int, DIAGONAL_DIRECTIONS, ORTHO_DIRECTIONS};
+
+use super::Dungeon;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TileKind {
+    #[default]
+    Empty,
+    Wall,
+    Room,
+    Corridor,
+    // corridor tile right at the room's edge
+    Door
+}
+impl TileKind {
+    pub fn is_floor(&self) -> bool {
+        matches!(self, Self::Room | Self::Corridor | Self::Door)
+    }
+}
+
+impl Dungeon {
+    pub fn get_room_tiles(&self) -> HashSet<Vector2Int> {
+        self.get_rooms()
+            .flat_map(|(_, r)| r.get_tiles())
+            .collect()
+    }
+    pub fn get_doors(&self) -> Vec<Vector2Int> {
+        let room_tiles = self.get_room_tiles();
+        let mut doors = self.tiles.iter()
+            .filter(|v| is_door(**v, &room_tiles))
+            .copied()
+            .collect::<Vec<_>>();
+        // keep the output stable
+        doors.sort();
+        doors
+    }
+    pub fn get_tile_map(&self) -> Grid<TileKind> {
+        // classified tiles with a one tile margin left for the walls
+        let bounds = self.get_padded_bounds(1);
+        let mut map = Grid::from_bounds(bounds.0, bounds.1, TileKind::Empty);
+        let room_tiles = self.get_room_tiles();
+
+        for v in self.tiles.iter() {
+            let kind = match (room_tiles.contains(v), is_door(*v, &room_tiles)) {
+                (true, _) => TileKind::Room,
+                (false, true) => TileKind::Door,
+                (false, false) => TileKind::Corridor
+            };
+            map.set(*v, kind);
+            for dir in ORTHO_DIRECTIONS.iter().chain(DIAGONAL_DIRECTIONS.iter()) {
+                let n = *v + *dir;
+                if !self.tiles.contains(&n) { map.set(n, TileKind::Wall) }
+            }
+        }
+        map
+    }
+    pub fn get_area_map(&self) -> Grid<Option<usize>> {
+        // area index of every room and internal corridor tile
+        // corridors joining the areas are left as None
+        let bounds = self.get_padded_bounds(1);
+        let mut map = Grid::from_bounds(bounds.0, bounds.1, None);
+        for (i, area) in self.areas.iter().enumerate() {
+            for v in area.paths.iter().flatten() {
+                map.set(*v, Some(i));
+            }
+        }
+        // rooms take priority over corridors passing through
+        for ((i, _), room) in self.get_rooms() {
+            for v in room.get_tiles() {
+                map.set(v, Some(i));
+            }
+        }
+        map
+    }
+}
+
+fn is_door(v: Vector2Int, room_tiles: &HashSet<Vector2Int>) -> bool {
+    !room_tiles.contains(&v) && ORTHO_DIRECTIONS.iter().any(|d| room_tiles.contains(&(v + *d)))
+}
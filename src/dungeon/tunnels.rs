@@ -1,11 +1,13 @@
 use rand::{
     distributions::WeightedIndex,
-    prelude::*
+    prelude::*,
+    rngs::StdRng
 };
 
 use crate::vectors::Vector2Int;
 
-#[derive(Default)]
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Tunneler {
     #[default]
     LShape,
@@ -13,7 +15,7 @@ pub enum Tunneler {
 }
 
 impl Tunneler {
-    pub fn get_connector(&self) -> impl Fn(Vector2Int, Vector2Int) -> Vec<Vector2Int> {
+    pub fn get_connector(&self) -> impl Fn(Vector2Int, Vector2Int, &mut StdRng) -> Vec<Vector2Int> {
         match self {
             Self::LShape => l_shape_connector,
             Self::Weighted => weighted_connector
@@ -21,17 +23,16 @@ impl Tunneler {
     }
 }
 
-pub fn weighted_connector (a: Vector2Int, b: Vector2Int) -> Vec<Vector2Int> {
+pub fn weighted_connector (a: Vector2Int, b: Vector2Int, rng: &mut StdRng) -> Vec<Vector2Int> {
     let mut cur = a;
     let mut path = Vec::new();
-    let mut rng = thread_rng();
 
     while cur != b {
         path.push(cur);
-        let dirs = vec![b.x - cur.x, b.y - cur.y];
+        let dirs = [b.x - cur.x, b.y - cur.y];
 
         let dist = WeightedIndex::new(dirs.iter().map(|d| d.abs())).unwrap();
-        let dir_idx = dist.sample(&mut rng);
+        let dir_idx = dist.sample(rng);
         let dv = match dir_idx {
             0 => Vector2Int::new(dirs[0] / dirs[0].abs(), 0),
             1 => Vector2Int::new(0, dirs[1] / dirs[1].abs()),
@@ -42,7 +43,7 @@ pub fn weighted_connector (a: Vector2Int, b: Vector2Int) -> Vec<Vector2Int> {
     path
 }
 
-pub fn l_shape_connector (a: Vector2Int, b: Vector2Int) -> Vec<Vector2Int> {
+pub fn l_shape_connector (a: Vector2Int, b: Vector2Int, _rng: &mut StdRng) -> Vec<Vector2Int> {
     let d = b - a;
     let (hor_y, ver_x) = match d.x > d.y {
         true => (a.y, b.x),
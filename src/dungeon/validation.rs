@@ -0,0 +1,123 @@
+use rand::{prelude::*, rngs::StdRng};
+use std::collections::HashSet;
+
+use crate::vectors::{find_path, Vector2Int};
+
+use super::{Corridor, Dungeon, Room, RoomRef};
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValidationReport {
+    pub start: Vector2Int,
+    // groups of mutually reachable rooms,
+    // the first one is always the group reachable from the start tile
+    pub components: Vec<Vec<RoomRef>>,
+    // tunnels dug by repair, always 0 for validate
+    pub tunnels: usize
+}
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.components.len() == 1
+    }
+    pub fn get_unreachable(&self) -> Vec<RoomRef> {
+        self.components[1..].concat()
+    }
+}
+
+impl Dungeon {
+    pub fn validate(&self, start: Vector2Int) -> ValidationReport {
+        // check that every room can be reached from the start tile
+        let blockers = HashSet::new();
+        let mut components: Vec<Vec<RoomRef>> = vec![Vec::new()];
+        // a single tile used to test reachability of each component
+        let mut anchors = vec![start];
+
+        for (room_ref, room) in self.get_rooms() {
+            let v = room.centre();
+            let idx = anchors.iter().enumerate().position(|(i, a)| {
+                if i == 0 && !self.tiles.contains(a) { return false }
+                find_path(*a, v, &self.tiles, &blockers).is_some()
+            });
+            match idx {
+                Some(i) => components[i].push(room_ref),
+                None => {
+                    anchors.push(v);
+                    components.push(vec![room_ref]);
+                }
+            }
+        }
+        ValidationReport { start, components, tunnels: 0 }
+    }
+    pub fn repair(&mut self, start: Vector2Int) -> ValidationReport {
+        // tunnel between the nearest components until all rooms are reachable
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut tunnels = 0;
+        loop {
+            let report = self.validate(start);
+            if report.is_valid() {
+                self.update_graph();
+                // the mission locks were placed by generate, before these tunnels
+                if tunnels > 0 { self.drop_bypassed_locks() }
+                return ValidationReport { tunnels, ..report }
+            }
+
+            let (a, b, path) = {
+                // if the start is not connected to any room, treat it as a single tile room
+                let start_room = Room::new(start, start);
+                let sources = match report.components[0].len() {
+                    0 => vec![(None, &start_room)],
+                    _ => report.components[0].iter().map(|r| (Some(*r), self.get_room(*r))).collect()
+                };
+                // rooms behind a lock are only tunneled to when nothing else is left
+                let locked = self.locks.iter().map(|l| l.room).collect::<HashSet<_>>();
+                let ((a, room), b) = sources.iter()
+                    .flat_map(|a| report.get_unreachable().into_iter().map(move |b| (*a, b)))
+                    .min_by_key(|((r, a), b)| (
+                        r.is_some_and(|r| locked.contains(&r)) || locked.contains(b),
+                        a.corner_distance(self.get_room(*b))
+                    ))
+                    .unwrap();
+                (a, b, room.join(self.get_room(b), &self.areas[b.0].tunneler, &mut rng))
+            };
+            self.tiles.extend(&path);
+            tunnels += 1;
+            // a tunnel from the bare start tile is not a room connection
+            match a {
+                Some(a) => self.corridors.push(Corridor::new(a, b, path)),
+                None => self.start_tunnels.push((b, path))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dungeon::{Area, ConnectionStrategy, CycleTemplate, RoomGenerator, Tunneler};
+    use super::*;
+
+    #[test]
+    fn repair_keeps_mission_locks_closed() {
+        let mut repaired = 0;
+        for seed in 0..16 {
+            let mut d = Dungeon::new();
+            d.seed = seed;
+            for _ in 0..3 {
+                d.add_area(Area::new(
+                    RoomGenerator::GrowSeparated { count: 6, min_size: 3, max_size: 5 },
+                    Tunneler::LShape,
+                    ConnectionStrategy::Cycle(CycleTemplate::LockKey)
+                ));
+            }
+            d.generate();
+            // a start away from the rooms, so repair has to tunnel from it
+            let start = d.get_bounds().unwrap().0 - Vector2Int::new(3, 3);
+            if d.repair(start).tunnels == 0 { continue }
+            repaired += 1;
+            for lock in d.locks.iter() {
+                let blockers = HashSet::from([lock.tile]);
+                assert!(find_path(start, d.get_room(lock.room).centre(), &d.tiles, &blockers).is_none(), "seed {}", seed);
+            }
+            assert_eq!((d.locks.clone(), d.keys.clone()), d.get_mission_locks(), "seed {}", seed);
+        }
+        assert!(repaired > 0);
+    }
+}
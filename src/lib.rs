@@ -0,0 +1,4 @@
+pub mod dungeon;
+#[cfg(feature = "recipe")]
+pub mod recipe;
+pub mod vectors;
@@ -1,22 +1,264 @@
+use std::path::Path;
 
-mod dungeon;
-mod vectors;
+use dun_gen::dungeon::{
+    AnimationSettings, AsciiSettings, Dungeon, GenerationEvent, LockSettings, SvgSettings,
+    TiledSettings
+};
+use dun_gen::recipe::{DungeonRecipe, StackRecipe};
 
-use dungeon::{Area, Dungeon, Tunneler, RoomGenerator, ConnectionStrategy};
+const DEFAULT_RECIPE: &str = include_str!("../recipes/default.toml");
+
+const USAGE: &str = "Usage: dun_gen [OPTIONS]
+
+Options:
+  -r, --recipe <FILE>    dungeon recipe (.toml or .ron), built-in default if not given
+      --stack <FILE>     stack recipe listing a recipe per floor, outputs get numbered
+                         by floor (see recipes/stack.toml)
+  -s, --seed <N>         seed, overrides the recipe's seed
+  -o, --output <PATH>    output path [default: output.<format>]
+  -f, --format <FORMAT>  png, svg, gif, json, tmx or ascii
+                         [default: from the output extension or png]
+      --scale <N>        pixels per tile for png, svg and gif output [default: 8]
+  -n, --count <N>        number of dungeons to generate, outputs get numbered [default: 1]
+      --locks <N>        number of locked doors with keys to place [default: 0]
+      --stats            print dungeon stats to stdout
+  -h, --help             print this message";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Png,
+    Svg,
+    Gif,
+    Json,
+    Tmx,
+    Ascii
+}
+impl Format {
+    fn from_str(s: &str) -> Option<Format> {
+        match s.to_lowercase().as_str() {
+            "png" => Some(Format::Png),
+            "svg" => Some(Format::Svg),
+            "gif" => Some(Format::Gif),
+            "json" => Some(Format::Json),
+            "tmx" => Some(Format::Tmx),
+            "ascii" | "txt" => Some(Format::Ascii),
+            _ => None
+        }
+    }
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Svg => "svg",
+            Self::Gif => "gif",
+            Self::Json => "json",
+            Self::Tmx => "tmx",
+            Self::Ascii => "txt"
+        }
+    }
+}
+
+struct Args {
+    recipe: Option<String>,
+    stack: Option<String>,
+    seed: Option<u64>,
+    output: String,
+    format: Format,
+    scale: u32,
+    count: usize,
+    locks: usize,
+    stats: bool
+}
+
+fn parse_args(mut args: impl Iterator<Item=String>) -> Result<Option<Args>, String> {
+    let mut recipe = None;
+    let mut stack = None;
+    let mut seed = None;
+    let mut output: Option<String> = None;
+    let mut format = None;
+    let mut scale = 8;
+    let mut count = 1;
+    let mut locks = 0;
+    let mut stats = false;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "-r" | "--recipe" => recipe = Some(value()?),
+            "--stack" => stack = Some(value()?),
+            "-s" | "--seed" => seed = Some(parse_number(&value()?)?),
+            "-o" | "--output" => output = Some(value()?),
+            "-f" | "--format" => {
+                let v = value()?;
+                format = Some(Format::from_str(&v).ok_or(format!("unknown format: {}", v))?);
+            },
+            "--scale" => scale = parse_number(&value()?)?,
+            "-n" | "--count" => count = parse_number(&value()?)?,
+            "--locks" => locks = parse_number(&value()?)?,
+            "--stats" => stats = true,
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(format!("unknown argument: {}", arg))
+        }
+    }
+    if scale == 0 { return Err("scale has to be at least 1".to_string()) }
+    if stack.is_some() && recipe.is_some() { return Err("--stack can't be used with --recipe".to_string()) }
+    if stack.is_some() && count != 1 { return Err("--stack can't be used with --count".to_string()) }
+
+    // the format falls back to the output extension
+    let format = format
+        .or_else(|| Format::from_str(Path::new(output.as_ref()?).extension()?.to_str()?))
+        .unwrap_or(Format::Png);
+    let output = output.unwrap_or(format!("output.{}", format.extension()));
+
+    Ok(Some(Args { recipe, stack, seed, output, format, scale, count, locks, stats }))
+}
+
+fn parse_number<T: std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("invalid number: {}", s))
+}
+
+fn get_output_path(path: &str, idx: usize, count: usize) -> String {
+    // number the outputs when generating a batch, eg. output_3.png
+    if count == 1 { return path.to_string() }
+    let p = Path::new(path);
+    let stem = p.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+    let name = match p.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}_{}.{}", stem, idx, ext),
+        None => format!("{}_{}", stem, idx)
+    };
+    p.with_file_name(name).to_string_lossy().to_string()
+}
+
+fn print_stats(d: &Dungeon, path: &str, tunnels: Option<usize>) {
+    let rooms = d.get_rooms().count();
+    println!("{}", path);
+    println!("  seed: {}", d.seed);
+    println!("  areas: {}, rooms: {}", d.areas.len(), rooms);
+    println!("  floor tiles: {}, doors: {}", d.tiles.len(), d.get_doors().len());
+    println!(
+        "  corridors: {}, loops: {}, dead ends: {}",
+        d.graph.edges.len(),
+        d.graph.get_cycles().len(),
+        d.graph.nodes.iter().filter(|r| d.graph.get_degree(**r) == 1).count()
+    );
+    if let Some(tunnels) = tunnels {
+        println!("  repair tunnels: {}", tunnels);
+    }
+    if !d.locks.is_empty() {
+        println!("  locks: {}, keys: {}", d.locks.len(), d.keys.len());
+    }
+    let skipped = d.get_skipped_missions();
+    if !skipped.is_empty() {
+        let areas = skipped.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+        println!("  areas without their mission lock: {}", areas.join(", "));
+    }
+    if !d.placements.is_empty() {
+        println!("  entities: {}", d.placements.len());
+    }
+    if let (Some(entrance), Some(exit)) = (d.entrance, d.exit) {
+        let distance = d.get_distance_map(&[entrance], None)
+            .get(exit)
+            .copied()
+            .flatten();
+        println!("  entrance: ({}, {}), exit: ({}, {})", entrance.x, entrance.y, exit.x, exit.y);
+        if let Some(distance) = distance {
+            println!("  entrance to exit distance: {}", distance);
+        }
+    }
+}
+
+fn save(d: &Dungeon, path: &str, args: &Args, events: &[GenerationEvent]) -> Result<(), String> {
+    match args.format {
+        Format::Png => d.save_img(path, args.scale).map_err(|e| e.to_string()),
+        Format::Svg => d.save_svg(path, &SvgSettings { scale: args.scale, ..Default::default() })
+            .map_err(|e| e.to_string()),
+        // step by step animation of the generation
+        Format::Gif => d.save_gif(path, events, &AnimationSettings { scale: args.scale, ..Default::default() })
+            .map_err(|e| e.to_string()),
+        Format::Json => {
+            let data = serde_json::to_string(d).map_err(|e| e.to_string())?;
+            std::fs::write(path, data).map_err(|e| e.to_string())
+        },
+        Format::Tmx => d.save_tmx(path, &TiledSettings::default()).map_err(|e| e.to_string()),
+        Format::Ascii => d.save_ascii(path, &AsciiSettings { room_ids: true, colour: false })
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn decorate(d: &mut Dungeon, recipe: &DungeonRecipe, args: &Args) {
+    // the passes run once the stairs are placed
+    d.assign_rooms(&recipe.rooms);
+    if args.locks > 0 {
+        d.place_locks(&LockSettings { locks: args.locks, ..Default::default() });
+    }
+    if let Some(placement) = &recipe.placement {
+        d.place_entities(placement);
+    }
+}
+
+fn run_stack(path: &str, args: &Args) -> Result<(), String> {
+    let mut recipe = StackRecipe::load(path).map_err(|e| e.to_string())?;
+    if args.seed.is_some() { recipe.seed = args.seed }
+    if args.format == Format::Gif { return Err("gif output is not supported for stacks".to_string()) }
+
+    let mut stack = recipe.build().map_err(|e| e.to_string())?;
+    stack.generate(&recipe.get_settings())?;
+    let count = stack.floors.len();
+    for (i, (d, floor)) in stack.floors.iter_mut().zip(recipe.floors.iter()).enumerate() {
+        decorate(d, floor, args);
+        let path = get_output_path(&args.output, i, count);
+        save(d, &path, args, &[])?;
+        if args.stats { print_stats(d, &path, None) }
+    }
+    Ok(())
+}
+
+fn run(args: Args) -> Result<(), String> {
+    if let Some(path) = &args.stack { return run_stack(path, &args) }
+    let recipe = match &args.recipe {
+        Some(path) => DungeonRecipe::load(path),
+        None => DungeonRecipe::from_toml(DEFAULT_RECIPE)
+    }.map_err(|e| e.to_string())?;
+
+    for i in 0..args.count {
+        let mut d = recipe.build().map_err(|e| e.to_string())?;
+        // consecutive seeds keep batches reproducible
+        if let Some(seed) = args.seed.or(recipe.seed) {
+            d.seed = seed.wrapping_add(i as u64);
+        }
+
+        let events = match args.format {
+            Format::Gif => d.record_generation(),
+            _ => {
+                d.generate();
+                Vec::new()
+            }
+        };
+        let start = d.areas[0].rooms[0].centre();
+        let tunnels = d.repair(start).tunnels;
+        d.place_stairs(1.);
+        decorate(&mut d, &recipe, &args);
+
+        let path = get_output_path(&args.output, i, args.count);
+        save(&d, &path, &args, &events)?;
+        if args.stats { print_stats(&d, &path, Some(tunnels)) }
+    }
+    Ok(())
+}
 
 fn main() {
-    let mut d = Dungeon::new();
-    d.add_area(Area::new(RoomGenerator::Grow { count: 4, min_size: 3, max_size: 6 }, Tunneler::Weighted, ConnectionStrategy::Basic));
-    d.add_area(Area::new(RoomGenerator::GrowSeparated { count: 4, min_size: 3, max_size: 6 }, Tunneler::Weighted, ConnectionStrategy::Secondary(12)));
-    d.add_area(Area::new(RoomGenerator::Chamber { min_size: 12, max_size: 15 }, Tunneler::Weighted, ConnectionStrategy::Basic));
-    d.add_area(Area::new(RoomGenerator::GrowSeparated { count: 5, min_size: 2, max_size: 4 }, Tunneler::LShape, ConnectionStrategy::Secondary(20)));
-    d.add_area(Area::new(RoomGenerator::Grow { count: 4, min_size: 3, max_size: 6 }, Tunneler::Weighted, ConnectionStrategy::Secondary(20)));
-
-    d.generate();
-    d.save_img("output.png", 8);
-    // for i in 0..12 {
-    //     let mut d = dungeon::Dungeon::new();
-    //     d.generate();
-    //     d.save_img(&format!("img_{}.png", i), 8);
-    // }
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        },
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = run(args) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
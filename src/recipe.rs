@@ -0,0 +1,209 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{fmt, fs, path::Path};
+
+use crate::dungeon::{
+    Area, ConnectionStrategy, DifficultyBand, Dungeon, DungeonStack, PlacementSettings,
+    RoomGenerator, RoomRule, StackSettings, Tunneler
+};
+
+#[derive(Debug)]
+pub enum RecipeError {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+    Ron(ron::error::SpannedError),
+    UnknownFormat(String),
+    Invalid(String)
+}
+impl fmt::Display for RecipeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "can't read the recipe: {}", e),
+            Self::Toml(e) => write!(f, "invalid TOML recipe: {}", e),
+            Self::Ron(e) => write!(f, "invalid RON recipe: {}", e),
+            Self::UnknownFormat(ext) => write!(f, "unknown recipe format: {:?} (expected .toml or .ron)", ext),
+            Self::Invalid(msg) => write!(f, "invalid recipe: {}", msg)
+        }
+    }
+}
+impl std::error::Error for RecipeError {}
+impl From<std::io::Error> for RecipeError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+impl From<toml::de::Error> for RecipeError {
+    fn from(e: toml::de::Error) -> Self {
+        Self::Toml(e)
+    }
+}
+impl From<ron::error::SpannedError> for RecipeError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        Self::Ron(e)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DungeonRecipe {
+    // a random seed is picked when not given
+    #[serde(default)]
+    pub seed: Option<u64>,
+    // number of rows the areas are laid out in
+    #[serde(default = "default_rows")]
+    pub rows: usize,
+    pub areas: Vec<AreaRecipe>,
+    // room tagging rules, applied with Dungeon::assign_rooms after generation
+    #[serde(default)]
+    pub rooms: Vec<RoomRule>,
+    // spawn tables, applied with Dungeon::place_entities after the rooms are tagged
+    #[serde(default)]
+    pub placement: Option<PlacementSettings>
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AreaRecipe {
+    pub generator: RoomGenerator,
+    #[serde(default)]
+    pub tunneler: Tunneler,
+    #[serde(default)]
+    pub connections: ConnectionStrategy,
+    // see Area::endpoint_spacing
+    #[serde(default)]
+    pub endpoint_spacing: Option<f32>,
+    #[serde(default)]
+    pub difficulty: Option<DifficultyBand>
+}
+
+fn default_rows() -> usize { 2 }
+
+impl DungeonRecipe {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RecipeError> {
+        let recipe: DungeonRecipe = read_file(path.as_ref())?;
+        recipe.validate()?;
+        Ok(recipe)
+    }
+    pub fn from_toml(data: &str) -> Result<Self, RecipeError> {
+        let recipe: DungeonRecipe = toml::from_str(data)?;
+        recipe.validate()?;
+        Ok(recipe)
+    }
+    pub fn from_ron(data: &str) -> Result<Self, RecipeError> {
+        let recipe: DungeonRecipe = ron::from_str(data)?;
+        recipe.validate()?;
+        Ok(recipe)
+    }
+    pub fn validate(&self) -> Result<(), RecipeError> {
+        if self.rows == 0 {
+            return Err(RecipeError::Invalid("rows has to be at least 1".to_string()))
+        }
+        if self.areas.is_empty() {
+            return Err(RecipeError::Invalid("the recipe has no areas".to_string()))
+        }
+        for (i, area) in self.areas.iter().enumerate() {
+            area.generator.validate()
+                .map_err(|e| RecipeError::Invalid(format!("area {}: {}", i, e)))?;
+            if area.endpoint_spacing.is_some_and(|s| s.is_nan() || s < 1.) {
+                return Err(RecipeError::Invalid(format!("area {}: endpoint_spacing has to be at least 1", i)))
+            }
+            if let Some(band) = &area.difficulty {
+                band.validate().map_err(|e| RecipeError::Invalid(format!("area {}: {}", i, e)))?;
+            }
+        }
+        if let Some(placement) = &self.placement {
+            placement.validate().map_err(RecipeError::Invalid)?;
+        }
+        Ok(())
+    }
+    pub fn build(&self) -> Result<Dungeon, RecipeError> {
+        // returns a dungeon ready to be generated
+        self.validate()?;
+        let mut dungeon = Dungeon::with_rows(self.rows);
+        if let Some(seed) = self.seed {
+            dungeon.seed = seed;
+        }
+        for area in self.areas.iter() {
+            let mut a = Area::new(
+                area.generator.clone(),
+                area.tunneler.clone(),
+                area.connections.clone()
+            );
+            a.endpoint_spacing = area.endpoint_spacing;
+            a.difficulty = area.difficulty.clone();
+            dungeon.add_area(a);
+        }
+        Ok(dungeon)
+    }
+}
+
+#[derive(Deserialize)]
+struct StackFile {
+    #[serde(default)]
+    seed: Option<u64>,
+    // paths relative to the stack recipe
+    floors: Vec<String>,
+    #[serde(default = "default_max_offset")]
+    max_offset: i32
+}
+
+fn default_max_offset() -> i32 { StackSettings::default().max_offset }
+
+// floor recipes, top to bottom
+#[derive(Clone, Debug)]
+pub struct StackRecipe {
+    // consecutive floors get consecutive seeds, the floor recipe seeds are used when not given
+    pub seed: Option<u64>,
+    pub floors: Vec<DungeonRecipe>,
+    // see StackSettings
+    pub max_offset: i32
+}
+impl StackRecipe {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RecipeError> {
+        // loads the stack file and every floor recipe it lists
+        let path = path.as_ref();
+        let file: StackFile = read_file(path)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let floors = file.floors.iter()
+            .map(|f| DungeonRecipe::load(dir.join(f)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let recipe = StackRecipe { seed: file.seed, floors, max_offset: file.max_offset };
+        recipe.validate()?;
+        Ok(recipe)
+    }
+    pub fn validate(&self) -> Result<(), RecipeError> {
+        if self.floors.is_empty() {
+            return Err(RecipeError::Invalid("the stack has no floors".to_string()))
+        }
+        if self.max_offset < 0 {
+            return Err(RecipeError::Invalid("max_offset can't be negative".to_string()))
+        }
+        for (i, floor) in self.floors.iter().enumerate() {
+            floor.validate().map_err(|e| RecipeError::Invalid(format!("floor {}: {}", i, e)))?;
+        }
+        Ok(())
+    }
+    pub fn get_settings(&self) -> StackSettings {
+        StackSettings { max_offset: self.max_offset, ..Default::default() }
+    }
+    pub fn build(&self) -> Result<DungeonStack, RecipeError> {
+        // returns a stack ready to be generated
+        self.validate()?;
+        let mut stack = DungeonStack::new();
+        for (i, floor) in self.floors.iter().enumerate() {
+            let mut dungeon = floor.build()?;
+            if let Some(seed) = self.seed {
+                dungeon.seed = seed.wrapping_add(i as u64);
+            }
+            stack.add_floor(dungeon);
+        }
+        Ok(stack)
+    }
+}
+
+fn read_file<T: DeserializeOwned>(path: &Path) -> Result<T, RecipeError> {
+    // the format is picked by the file extension
+    let data = fs::read_to_string(path)?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => Ok(toml::from_str(&data)?),
+        Some("ron") => Ok(ron::from_str(&data)?),
+        ext => Err(RecipeError::UnknownFormat(ext.unwrap_or_default().to_string()))
+    }
+}
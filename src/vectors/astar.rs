@@ -0,0 +1,236 @@
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use super::{DIAGONAL_DIRECTIONS, ORTHO_DIRECTIONS, Vector2Int};
+use super::utils::{rebuild_path, Node, PathResult};
+
+// step costs are scaled so diagonal moves can be approximated with integers
+pub const STEP_COST: u32 = 10;
+pub const DIAGONAL_STEP_COST: u32 = 14;
+
+// returns a cost multiplier for entering a tile, None if it can't be entered
+// multipliers below 1 are raised to 1, so the heuristics never overestimate
+pub type CostFunc<'a> = Box<dyn Fn(Vector2Int) -> Option<u32> + 'a>;
+
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Heuristic {
+    // manhattan for orthogonal movement, octile with diagonals
+    #[default]
+    Auto,
+    // only admissible without diagonal movement
+    Manhattan,
+    Octile,
+    Chebyshev
+}
+impl Heuristic {
+    pub fn get_estimate(&self, a: Vector2Int, b: Vector2Int) -> u32 {
+        let dx = (a.x - b.x).unsigned_abs();
+        let dy = (a.y - b.y).unsigned_abs();
+        match self {
+            Self::Manhattan => STEP_COST * (dx + dy),
+            // never overestimates, whatever the movement
+            Self::Octile | Self::Auto => STEP_COST * dx.max(dy) + (DIAGONAL_STEP_COST - STEP_COST) * dx.min(dy),
+            Self::Chebyshev => STEP_COST * dx.max(dy)
+        }
+    }
+    pub fn resolve(&self, diagonal: DiagonalMovement) -> Heuristic {
+        // the best admissible heuristic for the movement, when set to Auto
+        match (self, diagonal) {
+            (Self::Auto, DiagonalMovement::Never) => Self::Manhattan,
+            (Self::Auto, _) => Self::Octile,
+            (h, _) => *h
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DiagonalMovement {
+    #[default]
+    Never,
+    Always,
+    // at least one of the two orthogonal neighbours has to be free
+    IfOneFree,
+    // both orthogonal neighbours have to be free
+    NoCornerCutting
+}
+impl DiagonalMovement {
+    fn get_directions(&self) -> Vec<Vector2Int> {
+        match self {
+            Self::Never => ORTHO_DIRECTIONS.to_vec(),
+            _ => [ORTHO_DIRECTIONS, DIAGONAL_DIRECTIONS].concat()
+        }
+    }
+    fn is_allowed(&self, v: Vector2Int, dir: Vector2Int, is_free: impl Fn(Vector2Int) -> bool) -> bool {
+        if dir.x == 0 || dir.y == 0 { return true }
+        let a = is_free(v + Vector2Int::new(dir.x, 0));
+        let b = is_free(v + Vector2Int::new(0, dir.y));
+        match self {
+            Self::Never => false,
+            Self::Always => true,
+            Self::IfOneFree => a || b,
+            Self::NoCornerCutting => a && b
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PathSettings<'a> {
+    pub heuristic: Heuristic,
+    pub diagonal: DiagonalMovement,
+    // per-tile cost multiplier, every tile costs 1 if not given
+    pub cost: Option<CostFunc<'a>>,
+    // max number of expanded nodes before the search gives up
+    pub max_explored: Option<usize>
+}
+
+pub fn find_path_astar(
+    start: Vector2Int,
+    end: Vector2Int,
+    tiles: &HashSet<Vector2Int>,
+    blockers: &HashSet<Vector2Int>,
+    settings: &PathSettings
+) -> Option<PathResult> {
+    let is_free = |v: Vector2Int| tiles.contains(&v) && !blockers.contains(&v);
+    let heuristic = settings.heuristic.resolve(settings.diagonal);
+
+    let mut queue = BinaryHeap::new();
+    queue.push(Node { v: start, cost: heuristic.get_estimate(start, end) });
+    let mut costs = HashMap::from([(start, 0)]);
+    let mut came_from = HashMap::new();
+    let mut closed = HashSet::new();
+
+    while let Some(Node { v, .. }) = queue.pop() {
+        if v == end {
+            let path = rebuild_path(start, end, &came_from);
+            return Some(PathResult {
+                cost: path.len() as u32,
+                path,
+                weighted_cost: costs[&end],
+                explored: closed.len()
+            })
+        }
+        if !closed.insert(v) { continue }
+        if let Some(max) = settings.max_explored {
+            if closed.len() > max { return None }
+        }
+        for dir in settings.diagonal.get_directions() {
+            let n = v + dir;
+            if closed.contains(&n) { continue }
+            if !tiles.contains(&n) { continue }
+            // we allow the target to be a blocker
+            if blockers.contains(&n) && n != end { continue }
+            if !settings.diagonal.is_allowed(v, dir, is_free) { continue }
+
+            let multiplier = match &settings.cost {
+                Some(f) => match f(n) {
+                    Some(c) => c.max(1),
+                    None => continue
+                },
+                None => 1
+            };
+            let step = match dir.x == 0 || dir.y == 0 {
+                true => STEP_COST,
+                false => DIAGONAL_STEP_COST
+            };
+            let new_cost = costs[&v] + step * multiplier;
+            match costs.get(&n) {
+                Some(c) if *c <= new_cost => (),
+                _ => {
+                    costs.insert(n, new_cost);
+                    came_from.insert(n, v);
+                    queue.push(Node { v: n, cost: new_cost + heuristic.get_estimate(n, end) });
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{prelude::*, rngs::StdRng};
+    use crate::vectors::find_path;
+    use super::*;
+
+    fn get_dijkstra_cost(
+        start: Vector2Int,
+        end: Vector2Int,
+        tiles: &HashSet<Vector2Int>,
+        diagonal: DiagonalMovement,
+        cost: impl Fn(Vector2Int) -> u32
+    ) -> Option<u32> {
+        // plain dijkstra with the same movement rules, as a reference
+        let mut costs = HashMap::from([(start, 0)]);
+        let mut queue = BinaryHeap::from([std::cmp::Reverse((0, start.x, start.y))]);
+        while let Some(std::cmp::Reverse((c, x, y))) = queue.pop() {
+            let v = Vector2Int::new(x, y);
+            if c > costs[&v] { continue }
+            for dir in diagonal.get_directions() {
+                let n = v + dir;
+                if !tiles.contains(&n) { continue }
+                if !diagonal.is_allowed(v, dir, |t| tiles.contains(&t)) { continue }
+                let step = if dir.x == 0 || dir.y == 0 { STEP_COST } else { DIAGONAL_STEP_COST };
+                let new_cost = c + step * cost(n).max(1);
+                if costs.get(&n).is_some_and(|o| *o <= new_cost) { continue }
+                costs.insert(n, new_cost);
+                queue.push(std::cmp::Reverse((new_cost, n.x, n.y)));
+            }
+        }
+        costs.get(&end).copied()
+    }
+
+    #[test]
+    fn default_heuristic_is_optimal() {
+        let mut rng = StdRng::seed_from_u64(29);
+        let modes = [
+            DiagonalMovement::Never, DiagonalMovement::Always,
+            DiagonalMovement::IfOneFree, DiagonalMovement::NoCornerCutting
+        ];
+        for i in 0..400 {
+            let tiles = (0..12).flat_map(|x| (0..12).map(move |y| Vector2Int::new(x, y)))
+                .filter(|_| rng.gen_bool(0.7))
+                .collect::<HashSet<_>>();
+            let mut sorted = tiles.iter().copied().collect::<Vec<_>>();
+            sorted.sort_by_key(|v| (v.y, v.x));
+            let (Some(start), Some(end)) = (sorted.choose(&mut rng).copied(), sorted.choose(&mut rng).copied()) else { continue };
+            // includes zero multipliers, which are raised to 1
+            let weights = sorted.iter().map(|v| (*v, rng.gen_range(0..4))).collect::<HashMap<_, _>>();
+            let diagonal = modes[i % modes.len()];
+
+            let settings = PathSettings {
+                diagonal,
+                cost: Some(Box::new(|v| weights.get(&v).copied())),
+                ..Default::default()
+            };
+            let result = find_path_astar(start, end, &tiles, &HashSet::new(), &settings);
+            let expected = get_dijkstra_cost(start, end, &tiles, diagonal, |v| weights[&v]);
+            assert_eq!(result.as_ref().map(|r| r.weighted_cost), expected, "map {}", i);
+            if let Some(r) = result {
+                assert_eq!(r.path.back().copied().unwrap_or(start), end);
+            }
+        }
+    }
+
+    #[test]
+    fn costs_match_find_path() {
+        // same units for both searches, on plain orthogonal movement
+        let mut rng = StdRng::seed_from_u64(31);
+        for i in 0..300 {
+            let tiles = (0..12).flat_map(|x| (0..12).map(move |y| Vector2Int::new(x, y)))
+                .filter(|_| rng.gen_bool(0.7))
+                .collect::<HashSet<_>>();
+            let mut sorted = tiles.iter().copied().collect::<Vec<_>>();
+            sorted.sort_by_key(|v| (v.y, v.x));
+            let (Some(start), Some(end)) = (sorted.choose(&mut rng).copied(), sorted.choose(&mut rng).copied()) else { continue };
+
+            let astar = find_path_astar(start, end, &tiles, &HashSet::new(), &PathSettings::default());
+            let plain = find_path(start, end, &tiles, &HashSet::new());
+            assert_eq!(astar.as_ref().map(|r| (r.cost, r.weighted_cost)), plain.as_ref().map(|r| (r.cost, r.weighted_cost)), "map {}", i);
+            if let Some(r) = astar {
+                assert_eq!(r.cost as usize, r.path.len());
+                assert_eq!(r.weighted_cost, r.cost * STEP_COST);
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use super::Vector2Int;
+
+// dense 2d storage covering a rectangular region
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Grid<T> {
+    pub origin: Vector2Int,
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<T>
+}
+impl<T: Clone> Grid<T> {
+    pub fn new(origin: Vector2Int, width: usize, height: usize, value: T) -> Self {
+        Grid {
+            origin,
+            width,
+            height,
+            values: vec![value; width * height]
+        }
+    }
+    pub fn from_bounds(a: Vector2Int, b: Vector2Int, value: T) -> Self {
+        // bounds are inclusive
+        let width = (b.x - a.x + 1).max(0) as usize;
+        let height = (b.y - a.y + 1).max(0) as usize;
+        Grid::new(a, width, height, value)
+    }
+    fn get_index(&self, v: Vector2Int) -> Option<usize> {
+        let d = v - self.origin;
+        if d.x < 0 || d.y < 0 || d.x as usize >= self.width || d.y as usize >= self.height {
+            return None
+        }
+        Some(d.y as usize * self.width + d.x as usize)
+    }
+    pub fn get(&self, v: Vector2Int) -> Option<&T> {
+        self.values.get(self.get_index(v)?)
+    }
+    pub fn get_mut(&mut self, v: Vector2Int) -> Option<&mut T> {
+        let idx = self.get_index(v)?;
+        self.values.get_mut(idx)
+    }
+    pub fn set(&mut self, v: Vector2Int, value: T) {
+        if let Some(cell) = self.get_mut(v) {
+            *cell = value;
+        }
+    }
+    pub fn iter(&self) -> impl Iterator<Item=(Vector2Int, &T)> {
+        self.values.iter()
+            .enumerate()
+            .map(|(i, t)| (
+                self.origin + Vector2Int::new((i % self.width) as i32, (i / self.width) as i32),
+                t
+            ))
+    }
+}
@@ -1,9 +1,19 @@
 use std::ops::{Add, AddAssign, Div, Mul, Sub, SubAssign};
 
+mod astar;
+mod grid;
+mod poisson;
 mod utils;
-pub use utils::find_path;
+pub use astar::{
+    find_path_astar, CostFunc, DiagonalMovement, Heuristic, PathSettings,
+    DIAGONAL_STEP_COST, STEP_COST
+};
+pub use grid::Grid;
+pub use poisson::poisson_disk_sample;
+pub use utils::{find_path, get_cost_map, get_distances, PathResult};
 
 #[derive(Copy, Clone, Debug, Default, Ord, PartialOrd, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vector2Int {
     pub x: i32,
     pub y: i32
@@ -31,7 +41,7 @@ impl Add for Vector2Int {
     type Output = Self;
 
     fn add(self, other: Self) -> Self {
-        return Vector2Int::new(self.x + other.x, self.y + other.y);
+        Vector2Int::new(self.x + other.x, self.y + other.y)
     }
 }
 
@@ -45,7 +55,7 @@ impl Sub for Vector2Int {
     type Output = Self;
 
     fn sub(self, other: Self) -> Self {
-        return Vector2Int::new(self.x - other.x, self.y - other.y)
+        Vector2Int::new(self.x - other.x, self.y - other.y)
     }
 }
 
@@ -59,7 +69,7 @@ impl Div<i32> for Vector2Int {
     type Output = Self;
 
     fn div(self, other: i32) -> Self {
-        return Vector2Int::new(self.x / other, self.y / other)
+        Vector2Int::new(self.x / other, self.y / other)
     }
 }
 
@@ -67,7 +77,7 @@ impl Mul<i32> for Vector2Int {
     type Output = Self;
 
     fn mul(self, other: i32) -> Self {
-        return Vector2Int::new(self.x * other, self.y * other)
+        Vector2Int::new(self.x * other, self.y * other)
     }
 }
 
@@ -75,7 +85,7 @@ impl Mul<Vector2Int> for i32 {
     type Output = Vector2Int;
 
     fn mul(self, other: Vector2Int) -> Vector2Int {
-        return Vector2Int::new(other.x * self, other.y * self)
+        Vector2Int::new(other.x * self, other.y * self)
     }
 }
 
@@ -83,3 +93,8 @@ pub const ORTHO_DIRECTIONS: [Vector2Int; 4] = [
     Vector2Int::UP, Vector2Int::DOWN,
     Vector2Int::LEFT, Vector2Int::RIGHT
 ];
+
+pub const DIAGONAL_DIRECTIONS: [Vector2Int; 4] = [
+    Vector2Int { x: 1, y: 1 }, Vector2Int { x: 1, y: -1 },
+    Vector2Int { x: -1, y: 1 }, Vector2Int { x: -1, y: -1 }
+];
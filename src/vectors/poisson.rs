@@ -0,0 +1,95 @@
+use rand::{prelude::*, rngs::StdRng};
+use std::collections::{HashMap, HashSet};
+
+use super::Vector2Int;
+
+pub fn poisson_disk_sample(
+    tiles: &HashSet<Vector2Int>,
+    radius: f32,
+    max_attempts: usize,
+    rng: &mut StdRng
+) -> Vec<Vector2Int> {
+    // bridson's algorithm over a set of tiles
+    // no two samples are closer than the radius (euclidean)
+    // the region can be disconnected, so it's reseeded until every tile
+    // is covered by a sample
+    let radius = radius.max(1.);
+    let cell_size = radius.ceil() as i32;
+    let get_cell = |v: Vector2Int| Vector2Int::new(v.x.div_euclid(cell_size), v.y.div_euclid(cell_size));
+
+    let mut cells: HashMap<Vector2Int, Vec<Vector2Int>> = HashMap::new();
+    let is_free = |v: Vector2Int, cells: &HashMap<Vector2Int, Vec<Vector2Int>>| {
+        let c = get_cell(v);
+        // the cell is as large as the radius, so the 3x3 block is enough
+        (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| c + Vector2Int::new(dx, dy)))
+            .filter_map(|n| cells.get(&n))
+            .flatten()
+            .all(|s| get_distance(*s, v) >= radius)
+    };
+
+    // sorted first, as the set's iteration order is random
+    let mut seeds = tiles.iter().copied().collect::<Vec<_>>();
+    seeds.sort_by_key(|v| (v.y, v.x));
+    seeds.shuffle(rng);
+
+    let mut samples = Vec::new();
+    for seed in seeds {
+        if !is_free(seed, &cells) { continue }
+        cells.entry(get_cell(seed)).or_default().push(seed);
+        samples.push(seed);
+        let mut active = vec![seed];
+
+        while !active.is_empty() {
+            let idx = rng.gen_range(0..active.len());
+            let base = active[idx];
+            let mut found = false;
+            for _ in 0..max_attempts {
+                // a random point in the ring between radius and 2 * radius
+                let angle = rng.gen_range(0. ..std::f32::consts::TAU);
+                let dist = rng.gen_range(radius..2. * radius);
+                let v = base + Vector2Int::new(
+                    (angle.cos() * dist).round() as i32,
+                    (angle.sin() * dist).round() as i32
+                );
+                if !tiles.contains(&v) || !is_free(v, &cells) { continue }
+                cells.entry(get_cell(v)).or_default().push(v);
+                samples.push(v);
+                active.push(v);
+                found = true;
+                break;
+            }
+            if !found { active.swap_remove(idx); }
+        }
+    }
+    samples
+}
+
+fn get_distance(a: Vector2Int, b: Vector2Int) -> f32 {
+    (((a.x - b.x).pow(2) + (a.y - b.y).pow(2)) as f32).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_keep_the_min_distance() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            // random blobs, often disconnected
+            let tiles = (0..400)
+                .map(|_| Vector2Int::new(rng.gen_range(0..30), rng.gen_range(0..20)))
+                .collect::<HashSet<_>>();
+            let radius = rng.gen_range(1. ..6.);
+            let samples = poisson_disk_sample(&tiles, radius, 30, &mut rng);
+
+            assert!(!samples.is_empty());
+            assert!(samples.iter().all(|v| tiles.contains(v)), "seed {}", seed);
+            for (i, a) in samples.iter().enumerate() {
+                for b in samples[i + 1..].iter() {
+                    assert!(get_distance(*a, *b) >= radius, "seed {}: {:?} {:?}", seed, a, b);
+                }
+            }
+        }
+    }
+}
@@ -4,51 +4,138 @@ use std::{
     collections::{BinaryHeap, HashMap, HashSet, VecDeque}
 };
 
-use super::{ORTHO_DIRECTIONS, Vector2Int};
+use super::{ORTHO_DIRECTIONS, STEP_COST, Vector2Int};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathResult {
+    // path tiles excluding the start, empty if start == end
+    pub path: VecDeque<Vector2Int>,
+    // number of steps, for both find_path and find_path_astar
+    pub cost: u32,
+    // step costs times the tile multipliers, scaled by STEP_COST
+    // (DIAGONAL_STEP_COST for diagonal steps), find_path's steps all cost STEP_COST
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub weighted_cost: u32,
+    // number of expanded nodes
+    pub explored: usize
+}
 
 pub fn find_path(
     start: Vector2Int,
     end: Vector2Int,
     tiles: &HashSet<Vector2Int>,
     blockers: &HashSet<Vector2Int>
-) -> Option<VecDeque<Vector2Int>> {
+) -> Option<PathResult> {
     
     let mut queue = BinaryHeap::new();
     queue.push(Node { v: start, cost: 0});
-    let mut visited = HashMap::new();
+    let mut costs = HashMap::from([(start, 0)]);
     let mut came_from = HashMap::new();
+    let mut visited = HashSet::new();
 
     while let Some(Node { v, cost }) = queue.pop() {
-        if v == end { break; }
-        visited.insert(v, cost);
+        if v == end {
+            return Some(PathResult {
+                path: rebuild_path(start, end, &came_from),
+                cost,
+                weighted_cost: cost * STEP_COST,
+                explored: visited.len()
+            })
+        }
+        // skip stale queue entries
+        if !visited.insert(v) { continue }
         for dir in ORTHO_DIRECTIONS {
             let n = v + dir;
             let new_cost = cost + 1;
-            if !tiles.contains(&n) { continue }
+            if !tiles.contains(&n) || visited.contains(&n) { continue }
             // we allow the target to be a blocker
             if blockers.contains(&n) && n != end { continue }
-            match visited.get(&n) {
+            // only keep the cheapest way to reach each node
+            match costs.get(&n) {
                 Some(c) if *c <= new_cost => (),
                 _ => {
-                    queue.push(Node { v: n, cost: new_cost });
+                    costs.insert(n, new_cost);
                     came_from.insert(n, v);
+                    queue.push(Node { v: n, cost: new_cost });
                 }
             }
         }
     }
+    None
+}
+
+pub(super) fn rebuild_path(
+    start: Vector2Int,
+    end: Vector2Int,
+    came_from: &HashMap<Vector2Int, Vector2Int>
+) -> VecDeque<Vector2Int> {
     let mut path = VecDeque::new();
     let mut cur = end;
-    while let Some(v) = came_from.get(&cur) {
+    while cur != start {
         path.push_front(cur);
-        cur = *v;
-        if cur == start { return Some(path) }
+        cur = came_from[&cur];
     }
-    None
+    path
+}
+
+pub fn get_distances(
+    start: Vector2Int,
+    tiles: &HashSet<Vector2Int>
+) -> HashMap<Vector2Int, u32> {
+    // bfs walking distance from the start to every reachable tile
+    let mut distances = HashMap::from([(start, 0)]);
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(v) = queue.pop_front() {
+        let cost = distances[&v] + 1;
+        for dir in ORTHO_DIRECTIONS {
+            let n = v + dir;
+            if !tiles.contains(&n) || distances.contains_key(&n) { continue }
+            distances.insert(n, cost);
+            queue.push_back(n);
+        }
+    }
+    distances
+}
+
+pub fn get_cost_map(
+    goals: &[Vector2Int],
+    tiles: &HashSet<Vector2Int>,
+    cost: impl Fn(Vector2Int) -> u32
+) -> HashMap<Vector2Int, u32> {
+    // multi-source dijkstra flood over the whole tile set
+    // the cost fn returns the cost of entering a tile
+    let mut queue = BinaryHeap::new();
+    let mut costs = HashMap::new();
+    for v in goals {
+        if !tiles.contains(v) { continue }
+        queue.push(Node { v: *v, cost: 0 });
+        costs.insert(*v, 0);
+    }
+    let mut visited = HashSet::new();
+
+    while let Some(Node { v, cost: cur }) = queue.pop() {
+        if !visited.insert(v) { continue }
+        for dir in ORTHO_DIRECTIONS {
+            let n = v + dir;
+            if !tiles.contains(&n) || visited.contains(&n) { continue }
+            let new_cost = cur + cost(n);
+            match costs.get(&n) {
+                Some(c) if *c <= new_cost => (),
+                _ => {
+                    costs.insert(n, new_cost);
+                    queue.push(Node { v: n, cost: new_cost });
+                }
+            }
+        }
+    }
+    costs
 }
 
 // helper struct for the path finder
 #[derive(Copy, Clone, Eq, PartialEq)]
-struct Node {
+pub(super) struct Node {
     pub v: Vector2Int,
     pub cost: u32
 }
@@ -64,4 +151,105 @@ impl PartialOrd for Node {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use rand::{prelude::*, rngs::StdRng};
+    use super::*;
+
+    fn get_bfs_cost(
+        start: Vector2Int,
+        end: Vector2Int,
+        tiles: &HashSet<Vector2Int>,
+        blockers: &HashSet<Vector2Int>
+    ) -> Option<u32> {
+        // brute force reference, with the same blocker rules as find_path
+        let mut distances = HashMap::from([(start, 0)]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(v) = queue.pop_front() {
+            if v == end { return Some(distances[&v]) }
+            for dir in ORTHO_DIRECTIONS {
+                let n = v + dir;
+                if !tiles.contains(&n) || distances.contains_key(&n) { continue }
+                if blockers.contains(&n) && n != end { continue }
+                distances.insert(n, distances[&v] + 1);
+                queue.push_back(n);
+            }
+        }
+        None
+    }
+
+    fn check_path(start: Vector2Int, end: Vector2Int, tiles: &HashSet<Vector2Int>, blockers: &HashSet<Vector2Int>, result: &PathResult) {
+        assert_eq!(result.cost as usize, result.path.len());
+        assert_eq!(result.path.back().copied().unwrap_or(start), end);
+        let mut prev = start;
+        for v in result.path.iter() {
+            assert_eq!(prev.manhattan(*v), 1);
+            assert!(tiles.contains(v));
+            assert!(!blockers.contains(v) || *v == end);
+            prev = *v;
+        }
+    }
+
+    fn get_grid(w: i32, h: i32) -> HashSet<Vector2Int> {
+        (0..w).flat_map(|x| (0..h).map(move |y| Vector2Int::new(x, y))).collect()
+    }
+
+    #[test]
+    fn find_path_matches_bfs() {
+        let mut rng = StdRng::seed_from_u64(30);
+        for i in 0..2000 {
+            let size = rng.gen_range(2..16);
+            let mut grid = get_grid(size, size).into_iter().collect::<Vec<_>>();
+            grid.sort_by_key(|v| (v.y, v.x));
+            let tiles = grid.iter().copied().filter(|_| rng.gen_bool(0.65)).collect::<HashSet<_>>();
+            let blockers = grid.iter().copied().filter(|_| rng.gen_bool(0.1)).collect::<HashSet<_>>();
+            // the end can be off the floor, to cover unreachable targets
+            let start = *grid.choose(&mut rng).unwrap();
+            let end = *grid.choose(&mut rng).unwrap();
+
+            let result = find_path(start, end, &tiles, &blockers);
+            assert_eq!(result.as_ref().map(|r| r.cost), get_bfs_cost(start, end, &tiles, &blockers), "map {}", i);
+            if let Some(result) = result {
+                check_path(start, end, &tiles, &blockers, &result);
+            }
+        }
+    }
+
+    #[test]
+    fn find_path_to_start() {
+        let tiles = get_grid(3, 3);
+        let v = Vector2Int::new(1, 1);
+        let result = find_path(v, v, &tiles, &HashSet::new()).unwrap();
+        assert!(result.path.is_empty());
+        assert_eq!(result.cost, 0);
+    }
+
+    #[test]
+    fn find_path_unreachable() {
+        // two rooms split by an empty column
+        let tiles = get_grid(7, 3).into_iter().filter(|v| v.x != 3).collect::<HashSet<_>>();
+        let start = Vector2Int::new(0, 1);
+        assert_eq!(find_path(start, Vector2Int::new(6, 1), &tiles, &HashSet::new()), None);
+        // target outside of the floor
+        assert_eq!(find_path(start, Vector2Int::new(0, 5), &tiles, &HashSet::new()), None);
+    }
+
+    #[test]
+    fn find_path_blockers() {
+        // a wall of blockers with a single gap at the bottom
+        let tiles = get_grid(5, 5);
+        let blockers = (0..4).map(|y| Vector2Int::new(2, y)).collect::<HashSet<_>>();
+        let start = Vector2Int::new(0, 0);
+        let end = Vector2Int::new(4, 0);
+        let result = find_path(start, end, &tiles, &blockers).unwrap();
+        check_path(start, end, &tiles, &blockers, &result);
+        assert_eq!(result.cost, 12);
+
+        // fully closed off, unless the target itself is the blocker
+        let blockers = (0..5).map(|y| Vector2Int::new(2, y)).collect::<HashSet<_>>();
+        assert_eq!(find_path(start, end, &tiles, &blockers), None);
+        let result = find_path(start, Vector2Int::new(2, 0), &tiles, &blockers).unwrap();
+        assert_eq!(result.cost, 2);
+    }
+}